@@ -0,0 +1,27 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+use rlox::{ast_printer, formatter};
+
+fuzz_target!(|data: &[u8]| {
+    let source = match std::str::from_utf8(data) {
+        Ok(source) => source,
+        Err(_) => return,
+    };
+    let statements = match Scanner::scan(source).and_then(|tokens| Parser::parse(&tokens)) {
+        Ok(statements) => statements,
+        Err(_) => return,
+    };
+    let _ = Resolver::resolve(&statements, &Interpreter::new().global.borrow());
+    ast_printer::tree(&statements);
+    ast_printer::sexpr(&statements);
+    // the formatter checks itself that the layout keeps the program's tokens
+    if let Err(errors) = formatter::format(source) {
+        for error in errors {
+            assert!(!error.to_string().starts_with("Formatter bug"), "{}", error);
+        }
+    }
+});
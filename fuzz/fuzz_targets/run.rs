@@ -0,0 +1,77 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use rlox::interpreter::{Hook, Interpreter};
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+use rlox::statement::Stmt;
+use rlox::token::{Token, TokenType};
+use rlox::value::LoxError;
+
+const MAX_STATEMENTS: usize = 10_000;
+// the fuzzer's stack is smaller than the one rlox runs the interpreter on
+const MAX_FRAMES: usize = 64;
+// programs made up by the fuzzer that use these are skipped, they must not
+// touch the file system or environment
+const SANDBOXED: &[&str] = &[
+    "read_file",
+    "write_file",
+    "append_file",
+    "exists",
+    "list_dir",
+    "mkdir",
+    "remove",
+    "open",
+    "read_line",
+    "close",
+    "setenv",
+];
+
+// Stops programs that loop or recurse for too long.
+struct StepLimit {
+    statements: usize,
+}
+
+impl Hook for StepLimit {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        _statement: &Stmt,
+    ) -> Result<(), LoxError> {
+        self.statements += 1;
+        if self.statements > MAX_STATEMENTS || interpreter.frames().len() > MAX_FRAMES {
+            return Err(LoxError::Standard("Step limit reached.".to_string()));
+        }
+        Ok(())
+    }
+}
+
+// Whether the program refers to any of the natives in `SANDBOXED`.
+fn uses_sandboxed(tokens: &[Token]) -> bool {
+    tokens.iter().any(|token| match &token.token_type {
+        TokenType::Identifier(name) => SANDBOXED.contains(&name.as_str()),
+        _ => false,
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let source = match std::str::from_utf8(data) {
+        Ok(source) => source,
+        Err(_) => return,
+    };
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(std::io::sink()));
+    interpreter.set_input(Box::new(std::io::empty()));
+    interpreter.set_hook(Box::new(StepLimit { statements: 0 }));
+    let tokens = match Scanner::scan(source) {
+        Ok(tokens) if !uses_sandboxed(&tokens) => tokens,
+        _ => return,
+    };
+    let statements = match Parser::parse(&tokens).and_then(|statements| {
+        Resolver::resolve(&statements, &interpreter.global.borrow()).map(|()| statements)
+    }) {
+        Ok(statements) => statements,
+        Err(_) => return,
+    };
+    let _ = interpreter.interpret(&statements);
+});
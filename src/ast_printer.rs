@@ -0,0 +1,330 @@
+use crate::expression::Expr;
+use crate::statement::{FuncStmt, Stmt, StmtKind};
+use crate::token::Token;
+use std::fmt::Write;
+
+// Lisp-style rendering, one top level statement per line, e.g. `(print (+ 1 2))`.
+pub fn sexpr(statements: &[Stmt]) -> String {
+    statements
+        .iter()
+        .map(|statement| statement_sexpr(statement) + "\n")
+        .collect()
+}
+
+// Indented rendering with one node per line and children nested below their parent.
+pub fn tree(statements: &[Stmt]) -> String {
+    let mut printer = TreePrinter {
+        output: String::new(),
+        depth: 0,
+    };
+    for statement in statements {
+        printer.statement(statement);
+    }
+    printer.output
+}
+
+fn statement_sexpr(statement: &Stmt) -> String {
+    match &statement.kind {
+        StmtKind::Block(statements) => parenthesize_statements("block", statements),
+        StmtKind::Expr(expression) => format!("(; {})", expression_sexpr(expression)),
+        StmtKind::Function(FuncStmt {
+            name, params, body, ..
+        }) => {
+            let params = params.iter().map(lexeme).collect::<Vec<_>>().join(" ");
+            parenthesize_statements(&format!("fun {} ({})", lexeme(name), params), body)
+        }
+        StmtKind::If(condition, then_branch, else_branch) => match else_branch {
+            Some(else_branch) => format!(
+                "(if {} {} {})",
+                expression_sexpr(condition),
+                statement_sexpr(then_branch),
+                statement_sexpr(else_branch)
+            ),
+            None => format!(
+                "(if {} {})",
+                expression_sexpr(condition),
+                statement_sexpr(then_branch)
+            ),
+        },
+        StmtKind::Print(expression) => format!("(print {})", expression_sexpr(expression)),
+        StmtKind::Var(name, Some(initializer)) => {
+            format!("(var {} {})", lexeme(name), expression_sexpr(initializer))
+        }
+        StmtKind::Var(name, None) => format!("(var {})", lexeme(name)),
+        StmtKind::Const(name, initializer) => {
+            format!("(const {} {})", lexeme(name), expression_sexpr(initializer))
+        }
+        StmtKind::While(condition, body) => format!(
+            "(while {} {})",
+            expression_sexpr(condition),
+            statement_sexpr(body)
+        ),
+        StmtKind::For(initializer, condition, increment, body) => format!(
+            "(for {} {} {} {})",
+            initializer
+                .as_ref()
+                .map_or("_".to_string(), |s| statement_sexpr(s)),
+            condition.as_ref().map_or("_".to_string(), expression_sexpr),
+            increment.as_ref().map_or("_".to_string(), expression_sexpr),
+            statement_sexpr(body)
+        ),
+        StmtKind::Ret(Some(value)) => format!("(return {})", expression_sexpr(value)),
+        StmtKind::Ret(None) => "(return)".to_string(),
+    }
+}
+
+fn parenthesize_statements(name: &str, statements: &[Stmt]) -> String {
+    let mut output = format!("({name}");
+    for statement in statements {
+        output.push(' ');
+        output.push_str(&statement_sexpr(statement));
+    }
+    output.push(')');
+    output
+}
+
+fn expression_sexpr(expression: &Expr) -> String {
+    match expression {
+        Expr::Literal(token) => lexeme(token),
+        Expr::Grouping(expression) => format!("(group {})", expression_sexpr(expression)),
+        Expr::Unary(operator, right) => parenthesize(&lexeme(operator), &[right]),
+        Expr::Binary(left, operator, right) | Expr::Logical(left, operator, right) => {
+            parenthesize(&lexeme(operator), &[left, right])
+        }
+        Expr::Variable(name) => lexeme(name),
+        Expr::Assignment(name, value) => {
+            format!("(= {} {})", lexeme(name), expression_sexpr(value))
+        }
+        Expr::Conditional(condition, then_branch, else_branch) => {
+            parenthesize("?:", &[condition, then_branch, else_branch])
+        }
+        Expr::Call(callee, _, arguments) => {
+            let mut output = format!("(call {}", expression_sexpr(callee));
+            for argument in arguments {
+                output.push(' ');
+                output.push_str(&expression_sexpr(argument));
+            }
+            output.push(')');
+            output
+        }
+    }
+}
+
+fn parenthesize(name: &str, expressions: &[&Expr]) -> String {
+    let mut output = format!("({name}");
+    for expression in expressions {
+        output.push(' ');
+        output.push_str(&expression_sexpr(expression));
+    }
+    output.push(')');
+    output
+}
+
+fn lexeme(token: &Token) -> String {
+    token.token_type.to_string()
+}
+
+struct TreePrinter {
+    output: String,
+    depth: usize,
+}
+
+impl TreePrinter {
+    fn line(&mut self, label: &str) {
+        let _ = writeln!(
+            self.output,
+            "{:indent$}{}",
+            "",
+            label,
+            indent = self.depth * 2
+        );
+    }
+
+    fn nested<F>(&mut self, label: &str, children: F)
+    where
+        F: FnOnce(&mut TreePrinter),
+    {
+        self.line(label);
+        self.depth += 1;
+        children(self);
+        self.depth -= 1;
+    }
+
+    fn statement(&mut self, statement: &Stmt) {
+        match &statement.kind {
+            StmtKind::Block(statements) => self.nested("Block", |printer| {
+                for statement in statements {
+                    printer.statement(statement);
+                }
+            }),
+            StmtKind::Expr(expression) => {
+                self.nested("Expression", |printer| printer.expression(expression));
+            }
+            StmtKind::Function(FuncStmt {
+                name,
+                params,
+                body,
+                doc,
+            }) => {
+                if let Some(doc) = doc {
+                    for line in doc.lines() {
+                        self.line(&format!("/// {line}"));
+                    }
+                }
+                let params = params.iter().map(lexeme).collect::<Vec<_>>().join(", ");
+                self.nested(
+                    &format!("Function {}({})", lexeme(name), params),
+                    |printer| {
+                        for statement in body {
+                            printer.statement(statement);
+                        }
+                    },
+                );
+            }
+            StmtKind::If(condition, then_branch, else_branch) => self.nested("If", |printer| {
+                printer.expression(condition);
+                printer.nested("Then", |printer| printer.statement(then_branch));
+                if let Some(else_branch) = else_branch {
+                    printer.nested("Else", |printer| printer.statement(else_branch));
+                }
+            }),
+            StmtKind::Print(expression) => {
+                self.nested("Print", |printer| printer.expression(expression));
+            }
+            StmtKind::Var(name, initializer) => {
+                self.nested(&format!("Var {}", lexeme(name)), |printer| {
+                    if let Some(initializer) = initializer {
+                        printer.expression(initializer);
+                    }
+                });
+            }
+            StmtKind::Const(name, initializer) => {
+                self.nested(&format!("Const {}", lexeme(name)), |printer| {
+                    printer.expression(initializer);
+                });
+            }
+            StmtKind::While(condition, body) => self.nested("While", |printer| {
+                printer.expression(condition);
+                printer.statement(body);
+            }),
+            StmtKind::For(initializer, condition, increment, body) => {
+                self.nested("For", |printer| {
+                    if let Some(initializer) = initializer {
+                        printer.nested("Initializer", |printer| printer.statement(initializer));
+                    }
+                    if let Some(condition) = condition {
+                        printer.nested("Condition", |printer| printer.expression(condition));
+                    }
+                    if let Some(increment) = increment {
+                        printer.nested("Increment", |printer| printer.expression(increment));
+                    }
+                    printer.statement(body);
+                });
+            }
+            StmtKind::Ret(value) => self.nested("Return", |printer| {
+                if let Some(value) = value {
+                    printer.expression(value);
+                }
+            }),
+        }
+    }
+
+    fn expression(&mut self, expression: &Expr) {
+        match expression {
+            Expr::Literal(token) => self.line(&format!("Literal {}", lexeme(token))),
+            Expr::Grouping(expression) => {
+                self.nested("Grouping", |printer| printer.expression(expression));
+            }
+            Expr::Unary(operator, right) => {
+                self.nested(&format!("Unary {}", lexeme(operator)), |printer| {
+                    printer.expression(right);
+                });
+            }
+            Expr::Binary(left, operator, right) => {
+                self.nested(&format!("Binary {}", lexeme(operator)), |printer| {
+                    printer.expression(left);
+                    printer.expression(right);
+                });
+            }
+            Expr::Logical(left, operator, right) => {
+                self.nested(&format!("Logical {}", lexeme(operator)), |printer| {
+                    printer.expression(left);
+                    printer.expression(right);
+                });
+            }
+            Expr::Variable(name) => self.line(&format!("Variable {}", lexeme(name))),
+            Expr::Assignment(name, value) => {
+                self.nested(&format!("Assignment {}", lexeme(name)), |printer| {
+                    printer.expression(value);
+                });
+            }
+            Expr::Conditional(condition, then_branch, else_branch) => {
+                self.nested("Conditional", |printer| {
+                    printer.expression(condition);
+                    printer.expression(then_branch);
+                    printer.expression(else_branch);
+                });
+            }
+            Expr::Call(callee, _, arguments) => self.nested("Call", |printer| {
+                printer.expression(callee);
+                for argument in arguments {
+                    printer.expression(argument);
+                }
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        Parser::parse(&Scanner::scan(source).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn sexpr_shows_precedence() {
+        let statements = parse("const c = -(1 + 2) * 3;\nprint f(3) or nil;");
+        assert_eq!(
+            sexpr(&statements),
+            "(const c (* (- (group (+ 1 2))) 3))\n(print (or (call f 3) nil))\n"
+        );
+    }
+
+    #[test]
+    fn sexpr_marks_missing_for_clauses() {
+        let statements = parse("for (;;) { var x; }");
+        assert_eq!(sexpr(&statements), "(for _ _ _ (block (var x)))\n");
+    }
+
+    #[test]
+    fn sexpr_prints_functions_and_conditionals() {
+        let statements = parse("fun f(a) { if (a > 1) return a ? 1 : 2; }");
+        assert_eq!(
+            sexpr(&statements),
+            "(fun f (a) (if (> a 1) (return (?: a 1 2))))\n"
+        );
+    }
+
+    #[test]
+    fn tree_nests_children_below_their_parent() {
+        let statements = parse("fun f(a) { if (a > 1) return a; }\nprint -a;");
+        let expected = "\
+Function f(a)
+  If
+    Binary >
+      Variable a
+      Literal 1
+    Then
+      Return
+        Variable a
+Print
+  Unary -
+    Variable a
+";
+        assert_eq!(tree(&statements), expected);
+    }
+}
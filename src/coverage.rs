@@ -0,0 +1,443 @@
+use crate::expression::Expr;
+use crate::interpreter::{Branch, Frame, Hook, Interpreter};
+use crate::statement::{FuncStmt, Stmt, StmtKind};
+use crate::value::LoxError;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::rc::Rc;
+
+struct BranchPoint {
+    line: usize,
+    // tells apart the branch points sharing a line
+    block: usize,
+    // times the branch was taken and not taken
+    counts: [usize; 2],
+}
+
+// Which statements, branches and functions of a program ran, and how often.
+pub struct Coverage {
+    // hits of every line holding a statement
+    lines: BTreeMap<usize, usize>,
+    branches: Vec<BranchPoint>,
+    // the source position of a branch point to its index in `branches`
+    branch_index: HashMap<(usize, usize), usize>,
+    // calls of every function, by name and definition line
+    functions: BTreeMap<(usize, String), usize>,
+}
+
+impl Coverage {
+    // Lists everything the program could run, so what never ran shows up too.
+    pub fn new(statements: &[Stmt]) -> Rc<RefCell<Coverage>> {
+        let mut coverage = Coverage {
+            lines: BTreeMap::new(),
+            branches: Vec::new(),
+            branch_index: HashMap::new(),
+            functions: BTreeMap::new(),
+        };
+        coverage.statements(statements);
+        Rc::new(RefCell::new(coverage))
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Stmt) {
+        if !matches!(statement.kind, StmtKind::Block(_)) {
+            self.lines.entry(statement.line).or_insert(0);
+        }
+        match &statement.kind {
+            StmtKind::Block(statements) => self.statements(statements),
+            StmtKind::Expr(expression) | StmtKind::Print(expression) => {
+                self.expression(expression);
+            }
+            StmtKind::Var(_, initializer) => {
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+            }
+            StmtKind::Const(_, initializer) => self.expression(initializer),
+            StmtKind::Ret(value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            StmtKind::Function(FuncStmt { name, body, .. }) => {
+                let function = (name.line, name.identifier().to_string());
+                self.functions.insert(function, 0);
+                self.statements(body);
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.branch_point(Branch::Statement(statement));
+                self.expression(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            StmtKind::While(condition, body) => {
+                self.branch_point(Branch::Statement(statement));
+                self.expression(condition);
+                self.statement(body);
+            }
+            StmtKind::For(initializer, condition, increment, body) => {
+                if let Some(initializer) = initializer {
+                    self.statement(initializer);
+                }
+                // without a condition the loop always goes on
+                if let Some(condition) = condition {
+                    self.branch_point(Branch::Statement(statement));
+                    self.expression(condition);
+                }
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                }
+                self.statement(body);
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &Expr) {
+        match expression {
+            Expr::Literal(_) | Expr::Variable(_) => {}
+            Expr::Grouping(expression) | Expr::Unary(_, expression) => {
+                self.expression(expression);
+            }
+            Expr::Assignment(_, value) => self.expression(value),
+            Expr::Binary(left, _, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expr::Logical(left, _, right) => {
+                self.branch_point(Branch::Logical(expression));
+                self.expression(left);
+                self.expression(right);
+            }
+            Expr::Conditional(condition, then_branch, else_branch) => {
+                self.expression(condition);
+                self.expression(then_branch);
+                self.expression(else_branch);
+            }
+            Expr::Call(callee, _, arguments) => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+        }
+    }
+
+    fn branch_point(&mut self, branch: Branch) {
+        let (line, column) = position(branch);
+        let block = self
+            .branches
+            .iter()
+            .filter(|point| point.line == line)
+            .count();
+        self.branch_index
+            .insert((line, column), self.branches.len());
+        self.branches.push(BranchPoint {
+            line,
+            block,
+            counts: [0, 0],
+        });
+    }
+
+    // The report in the lcov tracefile format.
+    pub fn lcov(&self, path: &str) -> String {
+        let mut output = String::new();
+        let _ = writeln!(output, "TN:");
+        let _ = writeln!(output, "SF:{path}");
+        for (line, name) in self.functions.keys() {
+            let _ = writeln!(output, "FN:{line},{name}");
+        }
+        for ((_, name), calls) in &self.functions {
+            let _ = writeln!(output, "FNDA:{calls},{name}");
+        }
+        let _ = writeln!(output, "FNF:{}", self.functions.len());
+        let _ = writeln!(output, "FNH:{}", self.functions_hit());
+        for point in &self.branches {
+            for (branch, count) in point.counts.iter().enumerate() {
+                // '-' marks a branch point that was never reached
+                let taken = if point.counts == [0, 0] {
+                    "-".to_string()
+                } else {
+                    count.to_string()
+                };
+                let _ = writeln!(
+                    output,
+                    "BRDA:{},{},{},{}",
+                    point.line, point.block, branch, taken
+                );
+            }
+        }
+        let _ = writeln!(output, "BRF:{}", self.branches.len() * 2);
+        let _ = writeln!(output, "BRH:{}", self.branches_hit());
+        for (line, hits) in &self.lines {
+            let _ = writeln!(output, "DA:{line},{hits}");
+        }
+        let _ = writeln!(output, "LF:{}", self.lines.len());
+        let _ = writeln!(output, "LH:{}", self.lines_hit());
+        let _ = writeln!(output, "end_of_record");
+        output
+    }
+
+    pub fn summary(&self) -> String {
+        let mut output = String::new();
+        let _ = writeln!(
+            output,
+            "Lines:     {}",
+            ratio(self.lines_hit(), self.lines.len())
+        );
+        let _ = writeln!(
+            output,
+            "Branches:  {}",
+            ratio(self.branches_hit(), self.branches.len() * 2)
+        );
+        let _ = writeln!(
+            output,
+            "Functions: {}",
+            ratio(self.functions_hit(), self.functions.len())
+        );
+
+        let missed: Vec<_> = self
+            .lines
+            .iter()
+            .filter(|(_, hits)| **hits == 0)
+            .map(|(line, _)| *line)
+            .collect();
+        if !missed.is_empty() {
+            let _ = writeln!(output, "Lines not run: {}", ranges(&missed));
+        }
+        for point in &self.branches {
+            match point.counts {
+                [0, 0] => {
+                    let _ = writeln!(output, "Branch never reached at line {}", point.line);
+                }
+                [0, _] => {
+                    let _ = writeln!(output, "Branch never taken at line {}", point.line);
+                }
+                [_, 0] => {
+                    let _ = writeln!(output, "Branch always taken at line {}", point.line);
+                }
+                _ => {}
+            }
+        }
+        output
+    }
+
+    fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|hits| **hits > 0).count()
+    }
+
+    fn branches_hit(&self) -> usize {
+        self.branches
+            .iter()
+            .flat_map(|point| point.counts.iter())
+            .filter(|count| **count > 0)
+            .count()
+    }
+
+    fn functions_hit(&self) -> usize {
+        self.functions.values().filter(|calls| **calls > 0).count()
+    }
+}
+
+// Branch points are told apart by where their condition or operator is in
+// the source, functions run copies of their body so AST nodes can't be used.
+fn position(branch: Branch) -> (usize, usize) {
+    let token = match branch {
+        Branch::Statement(Stmt {
+            kind:
+                StmtKind::If(condition, ..)
+                | StmtKind::While(condition, _)
+                | StmtKind::For(_, Some(condition), ..),
+            ..
+        }) => condition.first_token(),
+        Branch::Logical(Expr::Logical(_, operator, _)) => operator,
+        Branch::Statement(statement) => return (statement.line, 0),
+        Branch::Logical(expression) => expression.first_token(),
+    };
+    (token.line, token.column)
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn ratio(hit: usize, found: usize) -> String {
+    if found == 0 {
+        return "0/0".to_string();
+    }
+    let percent = hit as f64 * 100.0 / found as f64;
+    format!("{hit}/{found} ({percent:.1}%)")
+}
+
+// Sorted lines written as "1, 4-6, 9".
+fn ranges(lines: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{start}-{end}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Records into a coverage shared with whoever reports it once the program ends.
+pub struct CoverageHook {
+    coverage: Rc<RefCell<Coverage>>,
+}
+
+impl CoverageHook {
+    pub fn new(coverage: &Rc<RefCell<Coverage>>) -> CoverageHook {
+        CoverageHook {
+            coverage: Rc::clone(coverage),
+        }
+    }
+}
+
+impl Hook for CoverageHook {
+    fn before_statement(
+        &mut self,
+        _interpreter: &mut Interpreter,
+        statement: &Stmt,
+    ) -> Result<(), LoxError> {
+        if !matches!(statement.kind, StmtKind::Block(_)) {
+            *self
+                .coverage
+                .borrow_mut()
+                .lines
+                .entry(statement.line)
+                .or_insert(0) += 1;
+        }
+        Ok(())
+    }
+
+    fn enter_function(&mut self, frames: &[Frame]) {
+        if let Some(frame) = frames.last() {
+            let function = (frame.definition_line, frame.name.clone());
+            *self
+                .coverage
+                .borrow_mut()
+                .functions
+                .entry(function)
+                .or_insert(0) += 1;
+        }
+    }
+
+    fn branch(&mut self, branch: Branch, taken: bool) {
+        let mut coverage = self.coverage.borrow_mut();
+        if let Some(&index) = coverage.branch_index.get(&position(branch)) {
+            coverage.branches[index].counts[usize::from(!taken)] += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::run_with_hook;
+
+    fn coverage(source: &str) -> Rc<RefCell<Coverage>> {
+        let mut coverage = None;
+        run_with_hook(source, |statements| {
+            Box::new(CoverageHook::new(
+                coverage.insert(Coverage::new(statements)),
+            ))
+        });
+        coverage.expect("the hook was made")
+    }
+
+    const SOURCE: &str = "\
+fun sign(n) {
+  if (n < 0) return -1;
+  return 1;
+}
+fun unused() {
+  print 0;
+}
+for (var i = 0; i < 2; i = i + 1) print sign(i) > 0 or false;
+";
+
+    #[test]
+    fn writes_lcov() {
+        let expected = "\
+TN:
+SF:test.lox
+FN:1,sign
+FN:5,unused
+FNDA:2,sign
+FNDA:0,unused
+FNF:2
+FNH:1
+BRDA:2,0,0,0
+BRDA:2,0,1,2
+BRDA:8,0,0,2
+BRDA:8,0,1,1
+BRDA:8,1,0,0
+BRDA:8,1,1,2
+BRF:6
+BRH:4
+DA:1,1
+DA:2,2
+DA:3,2
+DA:5,1
+DA:6,0
+DA:8,4
+LF:6
+LH:5
+end_of_record
+";
+        assert_eq!(coverage(SOURCE).borrow().lcov("test.lox"), expected);
+    }
+
+    #[test]
+    fn summarizes_what_never_ran() {
+        let expected = "\
+Lines:     5/6 (83.3%)
+Branches:  4/6 (66.7%)
+Functions: 1/2 (50.0%)
+Lines not run: 6
+Branch never taken at line 2
+Branch never taken at line 8
+";
+        assert_eq!(coverage(SOURCE).borrow().summary(), expected);
+    }
+
+    #[test]
+    fn reports_branches_never_reached() {
+        let source = "fun f(a) {\n  while (a) a = false;\n}\nif (true) print 1;\n";
+        let summary = coverage(source).borrow().summary();
+        assert!(summary.contains("Lines not run: 2\n"));
+        assert!(summary.contains("Branch never reached at line 2\n"));
+        assert!(summary.contains("Branch always taken at line 4\n"));
+    }
+
+    #[test]
+    fn tells_apart_branch_points_on_one_line() {
+        let source = "var a = true;\nprint (a and a) or (a and false);\n";
+        let coverage = coverage(source);
+        let coverage = coverage.borrow();
+        let blocks: Vec<_> = coverage.branches.iter().map(|point| point.block).collect();
+        assert_eq!(blocks, [0, 1, 2]);
+    }
+
+    #[test]
+    fn writes_line_ranges() {
+        assert_eq!(ranges(&[1, 4, 5, 6, 9]), "1, 4-6, 9");
+        assert_eq!(ranges(&[]), "");
+        assert_eq!(ratio(0, 0), "0/0");
+    }
+}
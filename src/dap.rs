@@ -0,0 +1,476 @@
+use crate::debugger::{self, Mode, Stepper};
+use crate::environment::Environment;
+use crate::interpreter::{Hook, Interpreter};
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
+use crate::statement::Stmt;
+use crate::value::LoxError;
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Stdin, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+// The interpreter is single threaded, this is the only thread reported.
+const THREAD_ID: u64 = 1;
+
+type DapResult<T> = Result<T, Box<dyn Error>>;
+
+// Serves the Debug Adapter Protocol over stdin and stdout for one launch of
+// a program, until the client disconnects.
+pub fn run() -> DapResult<()> {
+    let transport = Rc::new(RefCell::new(Transport::new()));
+    let mut stepper = Stepper::new(Mode::Continue);
+    let mut program = None;
+    let mut configured = false;
+
+    // the program starts once it is launched and the breakpoints are set
+    while program.is_none() || !configured {
+        let mut transport = transport.borrow_mut();
+        let Some(request) = transport.receive()? else {
+            return Ok(());
+        };
+        match request.command.as_str() {
+            "initialize" => {
+                let capabilities = json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsEvaluateForHovers": true,
+                    "supportsTerminateRequest": true,
+                });
+                transport.respond(&request, capabilities)?;
+                transport.event("initialized", json!({}))?;
+            }
+            "launch" => match Launch::new(&request.arguments) {
+                Ok(launch) => {
+                    program = Some(launch);
+                    transport.respond(&request, json!({}))?;
+                }
+                Err(message) => transport.fail(&request, &message)?,
+            },
+            "setBreakpoints" => {
+                let breakpoints = set_breakpoints(&mut stepper, &request.arguments);
+                transport.respond(&request, breakpoints)?;
+            }
+            "configurationDone" => {
+                configured = true;
+                transport.respond(&request, json!({}))?;
+            }
+            "disconnect" => return transport.respond(&request, json!({})),
+            _ => transport.answer(&request)?,
+        }
+    }
+    let program = program.ok_or("the program was never launched")?;
+
+    if program.stop_on_entry {
+        stepper.mode = Mode::StepInto;
+    }
+    let mut interpreter = Interpreter::new();
+    interpreter.set_output(Box::new(Output {
+        transport: Rc::clone(&transport),
+    }));
+    // stdin carries the protocol, so the program reads no input
+    interpreter.set_input(Box::new(std::io::empty()));
+    interpreter.set_hook(Box::new(DapHook {
+        transport: Rc::clone(&transport),
+        stepper,
+        path: program.path,
+        variables: Vec::new(),
+        started: false,
+    }));
+    let statements = Scanner::scan(&program.source)
+        .and_then(|tokens| Parser::parse(&tokens))
+        .and_then(|statements| {
+            Resolver::resolve(&statements, &interpreter.global.borrow()).map(|()| statements)
+        });
+    let (exit_code, errors) = match statements {
+        Ok(statements) => match interpreter.interpret(&statements) {
+            Ok(()) => (0, Vec::new()),
+            Err(LoxError::Exit(code)) => (code, Vec::new()),
+            Err(error) => (70, vec![error]),
+        },
+        Err(errors) => (65, errors),
+    };
+    drop(interpreter);
+
+    let mut transport = transport.borrow_mut();
+    if transport.disconnected {
+        return Ok(());
+    }
+    // stopping on request is not an error worth reporting
+    if !transport.stopped {
+        for error in errors {
+            let output = json!({ "category": "stderr", "output": format!("Error: {error}\n") });
+            transport.event("output", output)?;
+        }
+    }
+    transport.event("exited", json!({ "exitCode": exit_code }))?;
+    transport.event("terminated", json!({}))?;
+    while let Some(request) = transport.receive()? {
+        if request.command == "disconnect" {
+            return transport.respond(&request, json!({}));
+        }
+        transport.answer(&request)?;
+    }
+    Ok(())
+}
+
+// The arguments of the launch request.
+struct Launch {
+    path: String,
+    source: String,
+    stop_on_entry: bool,
+}
+
+impl Launch {
+    fn new(arguments: &Value) -> Result<Launch, String> {
+        let path = arguments["program"]
+            .as_str()
+            .ok_or("The launch request needs a 'program' path.")?;
+        let source = std::fs::read_to_string(path)
+            .map_err(|error| format!("Unable to read '{path}': {error}"))?;
+        Ok(Launch {
+            path: path.to_string(),
+            source,
+            stop_on_entry: arguments["stopOnEntry"].as_bool().unwrap_or(false),
+        })
+    }
+}
+
+// Replaces the breakpoints, there is only one source so its path is ignored.
+fn set_breakpoints(stepper: &mut Stepper, arguments: &Value) -> Value {
+    let lines = arguments["breakpoints"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|breakpoint| breakpoint["line"].as_u64())
+        .filter_map(|line| usize::try_from(line).ok());
+    stepper.breakpoints = lines.collect();
+    let breakpoints = stepper
+        .breakpoints
+        .iter()
+        .map(|line| json!({ "verified": true, "line": line }))
+        .collect::<Vec<_>>();
+    json!({ "breakpoints": breakpoints })
+}
+
+struct Request {
+    seq: u64,
+    command: String,
+    arguments: Value,
+}
+
+// Reads and writes the Content-Length framed messages of the protocol.
+struct Transport {
+    reader: BufReader<Stdin>,
+    seq: u64,
+    // the client went away, nothing more is read or sent
+    disconnected: bool,
+    // the client asked for the program to stop
+    stopped: bool,
+}
+
+impl Transport {
+    fn new() -> Transport {
+        Transport {
+            reader: BufReader::new(std::io::stdin()),
+            seq: 0,
+            disconnected: false,
+            stopped: false,
+        }
+    }
+
+    // The next request, or None once the client closed its end.
+    fn receive(&mut self) -> DapResult<Option<Request>> {
+        let mut length = None;
+        let length = loop {
+            let mut header = String::new();
+            if self.reader.read_line(&mut header)? == 0 {
+                self.disconnected = true;
+                return Ok(None);
+            }
+            let header = header.trim();
+            if header.is_empty() {
+                break length.ok_or("message without a Content-Length header")?;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = Some(value.trim().parse::<usize>()?);
+            }
+        };
+
+        let mut body = vec![0; length];
+        self.reader.read_exact(&mut body)?;
+        let message: Value = serde_json::from_slice(&body)?;
+        Ok(Some(Request {
+            seq: message["seq"].as_u64().unwrap_or(0),
+            command: message["command"].as_str().unwrap_or_default().to_string(),
+            arguments: message["arguments"].clone(),
+        }))
+    }
+
+    fn send(&mut self, mut message: Value) -> DapResult<()> {
+        self.seq += 1;
+        message["seq"] = json!(self.seq);
+        let text = serde_json::to_string(&message)?;
+        let mut stdout = std::io::stdout().lock();
+        write!(stdout, "Content-Length: {}\r\n\r\n{}", text.len(), text)?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    fn respond(&mut self, request: &Request, body: Value) -> DapResult<()> {
+        let mut response = json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": true,
+            "command": request.command,
+        });
+        response["body"] = body;
+        self.send(response)
+    }
+
+    fn fail(&mut self, request: &Request, message: &str) -> DapResult<()> {
+        self.send(json!({
+            "type": "response",
+            "request_seq": request.seq,
+            "success": false,
+            "command": request.command,
+            "message": message,
+        }))
+    }
+
+    fn event(&mut self, event: &str, body: Value) -> DapResult<()> {
+        let mut message = json!({ "type": "event", "event": event });
+        message["body"] = body;
+        self.send(message)
+    }
+
+    // Answers the requests that don't depend on a paused program.
+    fn answer(&mut self, request: &Request) -> DapResult<()> {
+        match request.command.as_str() {
+            "threads" => {
+                let threads = json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] });
+                self.respond(request, threads)
+            }
+            "setExceptionBreakpoints" => self.respond(request, json!({})),
+            command => self.fail(request, &format!("Unsupported request '{command}'.")),
+        }
+    }
+}
+
+// Sends the output of the program to the client, stdout carries the protocol.
+struct Output {
+    transport: Rc<RefCell<Transport>>,
+}
+
+impl Write for Output {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        let output = json!({
+            "category": "stdout",
+            "output": String::from_utf8_lossy(buffer),
+        });
+        self.transport
+            .borrow_mut()
+            .event("output", output)
+            .map_err(|error| std::io::Error::other(error.to_string()))?;
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// Pauses the program where the client asked and answers its requests until
+// it resumes the program.
+struct DapHook {
+    transport: Rc<RefCell<Transport>>,
+    stepper: Stepper,
+    path: String,
+    // scopes handed to the client while paused, a reference is the index plus one
+    variables: Vec<Rc<RefCell<Environment>>>,
+    // whether the program paused before
+    started: bool,
+}
+
+impl DapHook {
+    // Whether the client resumed the program rather than stopping it.
+    fn pause(&mut self, interpreter: &mut Interpreter, reason: &str) -> DapResult<bool> {
+        let stopped = json!({ "reason": reason, "threadId": THREAD_ID, "allThreadsStopped": true });
+        self.transport.borrow_mut().event("stopped", stopped)?;
+
+        let resumed = loop {
+            let request = self.transport.borrow_mut().receive()?;
+            let Some(request) = request else {
+                break false;
+            };
+            let depth = interpreter.frames().len();
+            let mode = match request.command.as_str() {
+                "continue" => Mode::Continue,
+                "next" => Mode::StepOver(depth),
+                "stepIn" => Mode::StepInto,
+                "stepOut" => Mode::StepOut(depth),
+                "disconnect" | "terminate" => {
+                    let mut transport = self.transport.borrow_mut();
+                    transport.respond(&request, json!({}))?;
+                    transport.stopped = true;
+                    transport.disconnected = request.command == "disconnect";
+                    break false;
+                }
+                _ => {
+                    self.inspect(interpreter, &request)?;
+                    continue;
+                }
+            };
+            self.stepper.mode = mode;
+            let body = json!({ "allThreadsContinued": true });
+            self.transport.borrow_mut().respond(&request, body)?;
+            break true;
+        };
+        self.variables.clear();
+        Ok(resumed)
+    }
+
+    // Answers a request that leaves the program paused.
+    fn inspect(&mut self, interpreter: &mut Interpreter, request: &Request) -> DapResult<()> {
+        let body = match request.command.as_str() {
+            "stackTrace" => self.stack_trace(interpreter),
+            "scopes" => {
+                let frame = request.arguments["frameId"]
+                    .as_u64()
+                    .and_then(|id| usize::try_from(id).ok())
+                    .and_then(|id| interpreter.frames().get(id));
+                match frame {
+                    Some(frame) => self.scopes(Rc::clone(&frame.environment)),
+                    None => return self.fail(request, "Unknown stack frame."),
+                }
+            }
+            "variables" => {
+                let scope = request.arguments["variablesReference"]
+                    .as_u64()
+                    .and_then(|reference| usize::try_from(reference).ok())
+                    .and_then(|reference| self.variables.get(reference.wrapping_sub(1)));
+                match scope {
+                    Some(scope) => variables(&scope.borrow()),
+                    None => return self.fail(request, "Unknown variables reference."),
+                }
+            }
+            "evaluate" => {
+                let expression = request.arguments["expression"].as_str().unwrap_or("");
+                // the transport is free here, the expression may print
+                match debugger::evaluate(interpreter, expression) {
+                    Ok(result) => json!({ "result": result, "variablesReference": 0 }),
+                    Err(errors) => {
+                        let messages = errors.iter().map(ToString::to_string);
+                        return self.fail(request, &messages.collect::<Vec<_>>().join("\n"));
+                    }
+                }
+            }
+            "setBreakpoints" => set_breakpoints(&mut self.stepper, &request.arguments),
+            _ => return self.transport.borrow_mut().answer(request),
+        };
+        self.transport.borrow_mut().respond(request, body)
+    }
+
+    fn fail(&self, request: &Request, message: &str) -> DapResult<()> {
+        self.transport.borrow_mut().fail(request, message)
+    }
+
+    // Innermost frame first, a frame id is its index in the interpreter's call stack.
+    fn stack_trace(&self, interpreter: &Interpreter) -> Value {
+        let name = Path::new(&self.path)
+            .file_name()
+            .map_or(self.path.clone(), |name| name.to_string_lossy().to_string());
+        let frames = interpreter
+            .frames()
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(id, frame)| {
+                json!({
+                    "id": id,
+                    "name": frame.name,
+                    "line": frame.line,
+                    "column": 1,
+                    "source": { "name": name, "path": self.path },
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({ "stackFrames": frames, "totalFrames": frames.len() })
+    }
+
+    fn scopes(&mut self, innermost: Rc<RefCell<Environment>>) -> Value {
+        let chain = debugger::scopes(innermost);
+        let count = chain.len();
+        let mut scopes = Vec::new();
+        for (depth, scope) in chain.into_iter().enumerate() {
+            let name = if depth + 1 == count {
+                "Globals".to_string()
+            } else if depth == 0 {
+                "Locals".to_string()
+            } else {
+                format!("Scope {depth}")
+            };
+            self.variables.push(scope);
+            scopes.push(json!({
+                "name": name,
+                "variablesReference": self.variables.len(),
+                "expensive": false,
+            }));
+        }
+        json!({ "scopes": scopes })
+    }
+}
+
+fn variables(scope: &Environment) -> Value {
+    let mut bindings: Vec<_> = scope.bindings().collect();
+    bindings.sort_by_key(|(name, ..)| *name);
+    let variables = bindings
+        .into_iter()
+        .map(|(name, value, mutable)| {
+            let kind = if mutable { "var" } else { "const" };
+            json!({
+                "name": name,
+                "value": value.to_string(),
+                "type": kind,
+                "variablesReference": 0,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({ "variables": variables })
+}
+
+impl Hook for DapHook {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        statement: &Stmt,
+    ) -> Result<(), LoxError> {
+        if !self.stepper.should_pause(interpreter, statement) {
+            return Ok(());
+        }
+
+        let reason = if self.stepper.breakpoints.contains(&statement.line) {
+            "breakpoint"
+        } else if self.started {
+            "step"
+        } else {
+            "entry"
+        };
+        self.started = true;
+        match self.pause(interpreter, reason) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(LoxError::Standard(
+                "Execution stopped by the debugger.".to_string(),
+            )),
+            Err(error) => {
+                self.transport.borrow_mut().disconnected = true;
+                Err(LoxError::Standard(format!(
+                    "Error: debug adapter failed: {error}"
+                )))
+            }
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use crate::environment::Environment;
+use crate::interpreter::{Hook, Interpreter};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::statement::{Stmt, StmtKind};
+use crate::value::LoxError;
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::rc::Rc;
+
+const COMMANDS: &[(&str, &str)] = &[
+    (
+        "break <line>",
+        "pause whenever the line is reached, alone lists breakpoints",
+    ),
+    ("delete <line>", "remove the breakpoint on the line"),
+    ("continue", "run until the next breakpoint"),
+    ("step", "run to the next line, entering called functions"),
+    ("next", "run to the next line in the current function"),
+    ("out", "run until the current function returns"),
+    (
+        "print <expr>",
+        "evaluate an expression in the paused function",
+    ),
+    ("env", "show the bindings of every enclosing scope"),
+    ("backtrace", "show the call stack"),
+    ("list", "show the source around the paused line"),
+    ("quit", "stop the program"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Continue,
+    StepInto,
+    // the call depth the step started from
+    StepOver(usize),
+    StepOut(usize),
+}
+
+// Decides where execution pauses. Steps and breakpoints work on lines, so
+// several statements on the same line only pause once.
+pub struct Stepper {
+    pub breakpoints: BTreeSet<usize>,
+    pub mode: Mode,
+    last_location: (usize, usize),
+    // line and column of the statements run at `last_location`, a loop coming
+    // back to one of them starts a new visit to the line
+    visited: Vec<(usize, usize)>,
+}
+
+impl Stepper {
+    pub fn new(mode: Mode) -> Stepper {
+        Stepper {
+            breakpoints: BTreeSet::new(),
+            mode,
+            last_location: (0, 0),
+            visited: Vec::new(),
+        }
+    }
+
+    pub fn should_pause(&mut self, interpreter: &Interpreter, statement: &Stmt) -> bool {
+        // a block only groups the statements that will pause on their own
+        if let StmtKind::Block(_) = statement.kind {
+            return false;
+        }
+        let depth = interpreter.frames().len();
+        let location = (statement.line, depth);
+        let position = (statement.line, statement.column);
+        if location == self.last_location && !self.visited.contains(&position) {
+            self.visited.push(position);
+            return false;
+        }
+        self.last_location = location;
+        self.visited = vec![position];
+
+        self.breakpoints.contains(&statement.line)
+            || match self.mode {
+                Mode::Continue => false,
+                Mode::StepInto => true,
+                Mode::StepOver(from) => depth <= from,
+                Mode::StepOut(from) => depth < from,
+            }
+    }
+}
+
+// Evaluates an expression in the environment of the paused function.
+pub fn evaluate(interpreter: &mut Interpreter, source: &str) -> Result<String, Vec<LoxError>> {
+    let expression = Scanner::scan(source).and_then(|tokens| Parser::parse_expression(&tokens))?;
+    interpreter
+        .interpret_expression(&expression)
+        .map(|value| value.to_string())
+        .map_err(|error| vec![error])
+}
+
+// The scopes visible from `innermost`, innermost first.
+pub fn scopes(innermost: Rc<RefCell<Environment>>) -> Vec<Rc<RefCell<Environment>>> {
+    let mut scopes = vec![innermost];
+    while let Some(enclosing) = scopes.last().and_then(|scope| scope.borrow().enclosing()) {
+        scopes.push(enclosing);
+    }
+    scopes
+}
+
+// Command line debugger reading commands from stdin whenever the program pauses.
+pub struct Debugger {
+    lines: Vec<String>,
+    stepper: Stepper,
+}
+
+impl Debugger {
+    pub fn new(source: &str) -> Debugger {
+        Debugger {
+            lines: source.lines().map(str::to_string).collect(),
+            // pause before the first statement so breakpoints can be set
+            stepper: Stepper::new(Mode::StepInto),
+        }
+    }
+
+    fn prompt(&mut self, interpreter: &mut Interpreter, line: usize) -> Result<(), LoxError> {
+        self.show_line(line);
+        loop {
+            print!("(debug) ");
+            let _ = std::io::stdout().flush();
+            let mut command = String::new();
+            match std::io::stdin().read_line(&mut command) {
+                Ok(0) | Err(_) => {
+                    // without input nothing can resume the program later
+                    self.stepper.breakpoints.clear();
+                    self.stepper.mode = Mode::Continue;
+                    return Ok(());
+                }
+                Ok(_) => {}
+            }
+
+            let command = command.trim();
+            let (name, argument) = command
+                .split_once(char::is_whitespace)
+                .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+            let depth = interpreter.frames().len();
+            match name {
+                "c" | "continue" => self.stepper.mode = Mode::Continue,
+                "s" | "step" => self.stepper.mode = Mode::StepInto,
+                "n" | "next" => self.stepper.mode = Mode::StepOver(depth),
+                "o" | "out" | "finish" => self.stepper.mode = Mode::StepOut(depth),
+                "q" | "quit" => {
+                    return Err(LoxError::Standard(
+                        "Execution stopped by the debugger.".to_string(),
+                    ))
+                }
+                _ => {
+                    self.run_command(interpreter, name, argument, line);
+                    continue;
+                }
+            }
+            return Ok(());
+        }
+    }
+
+    // Runs a command that inspects the program without resuming it.
+    fn run_command(
+        &mut self,
+        interpreter: &mut Interpreter,
+        name: &str,
+        argument: &str,
+        line: usize,
+    ) {
+        match name {
+            "b" | "break" if argument.is_empty() => {
+                for breakpoint in &self.stepper.breakpoints {
+                    println!("breakpoint at line {breakpoint}");
+                }
+            }
+            "b" | "break" => match argument.parse() {
+                Ok(line) => {
+                    self.stepper.breakpoints.insert(line);
+                }
+                Err(_) => println!("Error: '{argument}' is not a line number"),
+            },
+            "d" | "delete" => match argument.parse() {
+                Ok(line) => {
+                    if !self.stepper.breakpoints.remove(&line) {
+                        println!("Error: no breakpoint at line {line}");
+                    }
+                }
+                Err(_) => println!("Error: '{argument}' is not a line number"),
+            },
+            "p" | "print" => match evaluate(interpreter, argument) {
+                Ok(value) => println!("{value}"),
+                Err(errors) => {
+                    for error in errors {
+                        println!("Error: {error}");
+                    }
+                }
+            },
+            "env" | "locals" => {
+                let scopes = scopes(interpreter.environment());
+                for (depth, scope) in scopes.iter().enumerate() {
+                    if depth + 1 == scopes.len() {
+                        println!("global scope:");
+                    } else {
+                        println!("scope {depth}:");
+                    }
+                    let scope = scope.borrow();
+                    let mut bindings: Vec<_> = scope.bindings().collect();
+                    bindings.sort_by_key(|(name, ..)| *name);
+                    for (name, value, mutable) in bindings {
+                        let keyword = if mutable { "var" } else { "const" };
+                        println!("  {keyword} {name} = {value}");
+                    }
+                }
+            }
+            "bt" | "backtrace" => {
+                for (index, frame) in interpreter.frames().iter().rev().enumerate() {
+                    println!("#{} {} at line {}", index, frame.name, frame.line);
+                }
+            }
+            "l" | "list" => {
+                let first = line.saturating_sub(3).max(1);
+                for number in first..=(line + 3).min(self.lines.len()) {
+                    let marker = if number == line { ">" } else { " " };
+                    println!("{} {:>4} {}", marker, number, self.lines[number - 1]);
+                }
+            }
+            "h" | "help" => {
+                for (command, description) in COMMANDS {
+                    println!("{command:<16}{description}");
+                }
+            }
+            "" => {}
+            _ => println!("Error: unknown command '{name}', try help"),
+        }
+    }
+
+    fn show_line(&self, line: usize) {
+        let text = self.lines.get(line - 1).map_or("", String::as_str);
+        println!("{line:>4} {text}");
+    }
+}
+
+impl Hook for Debugger {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        statement: &Stmt,
+    ) -> Result<(), LoxError> {
+        if self.stepper.should_pause(interpreter, statement) {
+            self.prompt(interpreter, statement.line)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::run_with_hook;
+    use crate::value::LoxValue;
+
+    // Records the lines execution pauses on, then switches to the mode
+    // `resume` picks for the call depth of the pause.
+    struct Recorder {
+        stepper: Stepper,
+        resume: fn(usize) -> Mode,
+        pauses: Rc<RefCell<Vec<usize>>>,
+    }
+
+    impl Hook for Recorder {
+        fn before_statement(
+            &mut self,
+            interpreter: &mut Interpreter,
+            statement: &Stmt,
+        ) -> Result<(), LoxError> {
+            if self.stepper.should_pause(interpreter, statement) {
+                self.pauses.borrow_mut().push(statement.line);
+                self.stepper.mode = (self.resume)(interpreter.frames().len());
+            }
+            Ok(())
+        }
+    }
+
+    fn pauses(
+        source: &str,
+        breakpoints: &[usize],
+        mode: Mode,
+        resume: fn(usize) -> Mode,
+    ) -> Vec<usize> {
+        let mut stepper = Stepper::new(mode);
+        stepper.breakpoints.extend(breakpoints);
+        let pauses = Rc::new(RefCell::new(Vec::new()));
+        run_with_hook(source, |_| {
+            Box::new(Recorder {
+                stepper,
+                resume,
+                pauses: Rc::clone(&pauses),
+            })
+        });
+        let pauses = pauses.borrow().clone();
+        pauses
+    }
+
+    fn breakpoints(source: &str, lines: &[usize]) -> Vec<usize> {
+        pauses(source, lines, Mode::Continue, |_| Mode::Continue)
+    }
+
+    #[test]
+    fn breakpoint_in_a_while_body_pauses_every_iteration() {
+        let source = "var i = 0;\nwhile (i < 3) {\n  i = i + 1;\n}\n";
+        assert_eq!(breakpoints(source, &[3]), [3, 3, 3]);
+    }
+
+    #[test]
+    fn breakpoint_in_a_for_body_pauses_every_iteration() {
+        let source = "for (var i = 0; i < 3; i = i + 1)\n  print i;\n";
+        assert_eq!(breakpoints(source, &[2]), [2, 2, 2]);
+    }
+
+    #[test]
+    fn breakpoint_on_a_one_line_loop_pauses_every_iteration() {
+        let source = "for (var i = 0; i < 3; i = i + 1) { print i; print i; }\n";
+        assert_eq!(breakpoints(source, &[1]), [1, 1, 1]);
+    }
+
+    #[test]
+    fn statements_on_one_line_pause_once() {
+        let source = "var a = 1; var b = 2;\nprint a + b;\n";
+        assert_eq!(
+            pauses(source, &[], Mode::StepInto, |_| Mode::StepInto),
+            [1, 2]
+        );
+    }
+
+    #[test]
+    fn step_into_enters_called_functions() {
+        let source = "fun f() {\n  return 1;\n}\nprint f();\nprint 2;\n";
+        assert_eq!(
+            pauses(source, &[], Mode::StepInto, |_| Mode::StepInto),
+            [1, 4, 2, 5]
+        );
+    }
+
+    #[test]
+    fn step_over_skips_called_functions() {
+        let source = "fun f() {\n  return 1;\n}\nprint f();\nprint 2;\n";
+        assert_eq!(
+            pauses(source, &[], Mode::StepInto, Mode::StepOver),
+            [1, 4, 5]
+        );
+    }
+
+    #[test]
+    fn step_out_runs_until_the_function_returns() {
+        let source = "fun f() {\n  print 1;\n  print 2;\n}\nf();\nprint 3;\n";
+        assert_eq!(pauses(source, &[2], Mode::Continue, Mode::StepOut), [2, 6]);
+    }
+
+    #[test]
+    fn evaluates_in_the_current_scope() {
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .global
+            .borrow_mut()
+            .define("a", &LoxValue::Integer(2))
+            .unwrap();
+        assert_eq!(evaluate(&mut interpreter, "a * 3").unwrap(), "6");
+        assert!(evaluate(&mut interpreter, "a *").is_err());
+    }
+}
@@ -8,7 +8,13 @@ use std::rc::Rc;
 #[derive(Debug, Clone)]
 pub struct Environment {
     enclosed: Option<Rc<RefCell<Environment>>>,
-    values: HashMap<String, LoxValue>,
+    values: HashMap<String, Binding>,
+}
+
+#[derive(Debug, Clone)]
+struct Binding {
+    value: LoxValue,
+    mutable: bool,
 }
 
 impl Environment {
@@ -26,43 +32,109 @@ impl Environment {
         }))
     }
 
-    pub fn define(&mut self, name: &str, value: &LoxValue) {
-        self.values.insert(name.to_string(), value.clone());
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosed.clone()
+    }
+
+    pub fn define(&mut self, name: &str, value: &LoxValue) -> Result<(), LoxError> {
+        self.insert(name, value, true)
+    }
+
+    pub fn define_constant(&mut self, name: &str, value: &LoxValue) -> Result<(), LoxError> {
+        self.insert(name, value, false)
+    }
+
+    // Redefining a variable replaces it, but a constant stays as it is.
+    fn insert(&mut self, name: &str, value: &LoxValue, mutable: bool) -> Result<(), LoxError> {
+        if self.is_constant(name) {
+            return Err(LoxError::Standard(format!(
+                "Cannot redeclare constant '{name}'."
+            )));
+        }
+        let binding = Binding {
+            value: value.clone(),
+            mutable,
+        };
+        self.values.insert(name.to_string(), binding);
+        Ok(())
+    }
+
+    // Every binding in this scope (not the enclosing ones) as (name, value, mutable).
+    pub fn bindings(&self) -> impl Iterator<Item = (&String, &LoxValue, bool)> {
+        self.values
+            .iter()
+            .map(|(name, binding)| (name, &binding.value, binding.mutable))
+    }
+
+    pub fn is_constant(&self, name: &str) -> bool {
+        self.values
+            .get(name)
+            .is_some_and(|binding| !binding.mutable)
     }
 
     pub fn assign(&mut self, name: &str, value: &LoxValue) -> Result<(), LoxError> {
-        if self.values.contains_key(name) {
-            self.values.insert(name.to_string(), value.clone());
+        if let Some(binding) = self.values.get_mut(name) {
+            if !binding.mutable {
+                return Err(LoxError::Standard(format!(
+                    "Cannot assign to constant '{name}'."
+                )));
+            }
+            binding.value = value.clone();
             return Ok(());
         }
 
         if let Some(ref mut enclosed) = self.enclosed {
-            let result = enclosed.borrow_mut().assign(name, value);
-            if result.is_ok() {
-                return result;
-            }
+            return enclosed.borrow_mut().assign(name, value);
         }
 
-        Err(LoxError::Standard(format!(
-            "Undefined variable '{}'.",
-            name
-        )))
+        Err(LoxError::Standard(format!("Undefined variable '{name}'.")))
     }
 
     pub fn get(&self, token: &Token) -> Result<LoxValue, LoxError> {
         if let TokenType::Identifier(identifier) = &token.token_type {
-            if let Some(value) = self.values.get(identifier) {
-                Ok(value.clone())
+            if let Some(binding) = self.values.get(identifier) {
+                Ok(binding.value.clone())
             } else if let Some(enclosed) = &self.enclosed {
                 enclosed.borrow_mut().get(token)
             } else {
                 Err(LoxError::Standard(format!(
-                    "Undefined variable: {}",
-                    identifier
+                    "Undefined variable: {identifier}"
                 )))
             }
         } else {
-            panic!("Compiler bug: unexpected token: {:?}", token);
+            Err(LoxError::Standard(format!(
+                "Interpreter bug: unexpected variable token: {token:?}"
+            )))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn define_replaces_a_variable() {
+        let environment = Environment::new();
+        let mut environment = environment.borrow_mut();
+        environment.define("x", &LoxValue::Integer(1)).unwrap();
+        environment.define("x", &LoxValue::Integer(2)).unwrap();
+        assert!(!environment.is_constant("x"));
+        assert_eq!(environment.values["x"].value, LoxValue::Integer(2));
+    }
+
+    #[test]
+    fn define_keeps_a_constant() {
+        let environment = Environment::new();
+        let mut environment = environment.borrow_mut();
+        environment
+            .define_constant("x", &LoxValue::Integer(1))
+            .unwrap();
+        let error = environment.define("x", &LoxValue::Integer(2)).unwrap_err();
+        assert_eq!(error.to_string(), "Cannot redeclare constant 'x'.");
+        assert!(environment
+            .define_constant("x", &LoxValue::Integer(3))
+            .is_err());
+        assert_eq!(environment.values["x"].value, LoxValue::Integer(1));
+    }
+}
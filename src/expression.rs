@@ -2,16 +2,35 @@ use crate::token::Token;
 
 #[derive(Debug, Clone)]
 pub enum Expr {
-    Number(f64),
-    String(String),
-    True,
-    False,
-    Nil,
+    // number, string, `true`, `false` or `nil` token
+    Literal(Token),
     Grouping(Box<Expr>),
     Unary(Token, Box<Expr>),
     Binary(Box<Expr>, Token, Box<Expr>),
     Variable(Token),
     Assignment(Token, Box<Expr>),
     Logical(Box<Expr>, Token, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
     Call(Box<Expr>, Token, Vec<Expr>),
 }
+
+impl Expr {
+    // Line of the first token of the expression.
+    pub fn line(&self) -> usize {
+        self.first_token().line
+    }
+
+    pub fn first_token(&self) -> &Token {
+        match self {
+            Expr::Literal(token)
+            | Expr::Unary(token, _)
+            | Expr::Variable(token)
+            | Expr::Assignment(token, _) => token,
+            Expr::Grouping(expression)
+            | Expr::Binary(expression, ..)
+            | Expr::Logical(expression, ..)
+            | Expr::Conditional(expression, ..)
+            | Expr::Call(expression, ..) => expression.first_token(),
+        }
+    }
+}
@@ -0,0 +1,514 @@
+use crate::expression::Expr;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::statement::{FuncStmt, Stmt, StmtKind};
+use crate::token::{Comment, Token};
+use crate::value::LoxError;
+use std::iter::Peekable;
+use std::vec::IntoIter;
+
+const INDENT: &str = "    ";
+const MAX_WIDTH: usize = 100;
+
+// Re-emits a program in the canonical layout. Comments are kept and placed
+// before the statement that follows them, or after the statement they trail.
+// A comment inside a statement stays next to the token it precedes.
+pub fn format(source: &str) -> Result<String, Vec<LoxError>> {
+    let (tokens, comments) = Scanner::scan_with_comments(source)?;
+    let statements = Parser::parse(&tokens)?;
+    let mut formatter = Formatter::new(&tokens, comments, MAX_WIDTH);
+    formatter.statements(&statements, usize::MAX);
+
+    // layout changes must never change the meaning of the program
+    let rescanned = Scanner::scan(&formatter.output)?;
+    if same_tokens(&tokens, &rescanned) {
+        Ok(formatter.output)
+    } else {
+        Err(vec![LoxError::Standard(
+            "Formatter bug: the formatted program differs from the original.".to_string(),
+        )])
+    }
+}
+
+struct Formatter {
+    output: String,
+    depth: usize,
+    width: usize,
+    comments: Peekable<IntoIter<Comment>>,
+    // line and column of every token, to find the token a comment precedes
+    positions: Vec<(usize, usize)>,
+    // source line of the last statement or comment written, used to keep blank lines
+    last_line: usize,
+    // no blank line is kept right after an opening brace
+    block_start: bool,
+}
+
+impl Formatter {
+    fn new(tokens: &[Token], comments: Vec<Comment>, width: usize) -> Formatter {
+        Formatter {
+            output: String::new(),
+            depth: 0,
+            width,
+            comments: comments.into_iter().peekable(),
+            positions: tokens
+                .iter()
+                .map(|token| (token.line, token.column))
+                .collect(),
+            last_line: 0,
+            block_start: true,
+        }
+    }
+
+    // Comments on `end_line`, the line of a closing brace, go after the brace.
+    fn statements(&mut self, statements: &[Stmt], end_line: usize) {
+        for statement in statements {
+            self.leading_comments(statement.line);
+            self.begin_line(statement.line);
+            self.statement(statement);
+            self.trailing_comments(statement.end_line.min(end_line - 1));
+            self.output.push('\n');
+            self.last_line = statement.end_line;
+        }
+        self.leading_comments(end_line);
+    }
+
+    // Writes the comments that start before `line` on lines of their own.
+    fn leading_comments(&mut self, line: usize) {
+        while let Some(comment) = self.comments.next_if(|comment| comment.line < line) {
+            self.begin_line(comment.line);
+            self.output.push_str(comment.text.trim_end());
+            self.output.push('\n');
+            self.last_line = comment.line + comment.text.trim_end().matches('\n').count();
+        }
+    }
+
+    // Writes the comments up to `line` after the code just written.
+    fn trailing_comments(&mut self, line: usize) {
+        let mut comments = Vec::new();
+        while let Some(comment) = self.comments.next_if(|comment| comment.line <= line) {
+            comments.push(comment);
+        }
+        self.trailing(comments);
+    }
+
+    // Writes the comments preceding `token` after the code just written and
+    // tells whether there were any, the caller then has to start a new line.
+    fn comments_before(&mut self, token: &Token) -> bool {
+        let position = (token.line, token.column);
+        let positions = &self.positions;
+        let mut comments = Vec::new();
+        while let Some(comment) = self.comments.next_if(|comment| {
+            positions
+                .get(comment.next_token)
+                .is_some_and(|next| *next <= position)
+        }) {
+            comments.push(comment);
+        }
+        let any = !comments.is_empty();
+        self.trailing(comments);
+        any
+    }
+
+    fn trailing(&mut self, comments: Vec<Comment>) {
+        for (index, comment) in comments.into_iter().enumerate() {
+            if index == 0 && comment.trailing {
+                self.output.push(' ');
+            } else {
+                self.output.push('\n');
+                self.indent();
+            }
+            self.output.push_str(comment.text.trim_end());
+        }
+    }
+
+    // Continues the code just written on a new line, one level deeper.
+    fn continuation(&mut self) {
+        self.output.push('\n');
+        self.depth += 1;
+        self.indent();
+        self.depth -= 1;
+    }
+
+    fn begin_line(&mut self, line: usize) {
+        if self.block_start {
+            self.block_start = false;
+        } else if line > self.last_line + 1 {
+            self.output.push('\n');
+        }
+        self.indent();
+    }
+
+    fn indent(&mut self) {
+        for _ in 0..self.depth {
+            self.output.push_str(INDENT);
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        self.output.push_str(text);
+    }
+
+    fn column(&self) -> usize {
+        let line_start = self.output.rfind('\n').map_or(0, |index| index + 1);
+        self.output[line_start..].chars().count()
+    }
+
+    fn statement(&mut self, statement: &Stmt) {
+        match &statement.kind {
+            StmtKind::Block(statements) => {
+                self.block(statements, statement.line, statement.end_line);
+            }
+            StmtKind::Expr(expression) => {
+                self.expression(expression);
+                self.write(";");
+            }
+            StmtKind::Function(FuncStmt {
+                name, params, body, ..
+            }) => {
+                let params = params
+                    .iter()
+                    .map(|param| param.lexeme.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.write(&format!("fun {}({}) ", name.lexeme, params));
+                self.block(body, statement.line, statement.end_line);
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.write("if (");
+                self.expression(condition);
+                self.write(") ");
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    // comments before the `else` end the line, `else` starts the next one
+                    let commented = self
+                        .comments
+                        .peek()
+                        .is_some_and(|comment| comment.line < else_branch.line);
+                    if commented {
+                        self.trailing_comments(else_branch.line - 1);
+                        self.output.push('\n');
+                        self.indent();
+                        self.write("else ");
+                    } else {
+                        self.write(" else ");
+                    }
+                    self.statement(else_branch);
+                }
+            }
+            StmtKind::Print(expression) => {
+                self.write("print ");
+                self.expression(expression);
+                self.write(";");
+            }
+            StmtKind::Var(name, initializer) => {
+                self.write(&format!("var {}", name.lexeme));
+                if let Some(initializer) = initializer {
+                    self.write(" = ");
+                    self.expression(initializer);
+                }
+                self.write(";");
+            }
+            StmtKind::Const(name, initializer) => {
+                self.write(&format!("const {} = ", name.lexeme));
+                self.expression(initializer);
+                self.write(";");
+            }
+            StmtKind::While(condition, body) => {
+                self.write("while (");
+                self.expression(condition);
+                self.write(") ");
+                self.statement(body);
+            }
+            StmtKind::For(initializer, condition, increment, body) => {
+                self.write("for (");
+                match initializer {
+                    Some(initializer) => self.statement(initializer),
+                    None => self.write(";"),
+                }
+                if let Some(condition) = condition {
+                    self.header_part(condition);
+                }
+                self.write(";");
+                if let Some(increment) = increment {
+                    self.header_part(increment);
+                }
+                self.write(") ");
+                self.statement(body);
+            }
+            StmtKind::Ret(value) => {
+                self.write("return");
+                if let Some(value) = value {
+                    self.write(" ");
+                    self.expression(value);
+                }
+                self.write(";");
+            }
+        }
+    }
+
+    // A clause of a for header, on a new line when comments precede it.
+    fn header_part(&mut self, expression: &Expr) {
+        if self.comments_before(expression.first_token()) {
+            self.continuation();
+        } else {
+            self.write(" ");
+        }
+        self.expression(expression);
+    }
+
+    // `line` and `end_line` are the lines of the statement owning the braces.
+    fn block(&mut self, statements: &[Stmt], line: usize, end_line: usize) {
+        let has_comments = self
+            .comments
+            .peek()
+            .is_some_and(|comment| comment.line < end_line);
+        if statements.is_empty() && !has_comments {
+            self.write("{}");
+            return;
+        }
+
+        self.write("{");
+        self.trailing_comments(line.min(end_line - 1));
+        self.output.push('\n');
+        self.last_line = line;
+        self.block_start = true;
+        self.depth += 1;
+        self.statements(statements, end_line);
+        self.depth -= 1;
+        self.block_start = false;
+        self.indent();
+        self.write("}");
+    }
+
+    fn expression(&mut self, expression: &Expr) {
+        match expression {
+            Expr::Literal(token) | Expr::Variable(token) => self.write(&token.lexeme),
+            Expr::Grouping(expression) => {
+                self.write("(");
+                self.expression(expression);
+                self.write(")");
+            }
+            Expr::Unary(operator, right) => {
+                self.write(&operator.lexeme);
+                self.expression(right);
+            }
+            Expr::Binary(..) | Expr::Logical(..) => self.operators(expression),
+            Expr::Assignment(name, value) => {
+                self.write(&format!("{} = ", name.lexeme));
+                self.expression(value);
+            }
+            Expr::Conditional(condition, then_branch, else_branch) => {
+                self.expression(condition);
+                self.write(" ? ");
+                self.expression(then_branch);
+                self.write(" : ");
+                self.expression(else_branch);
+            }
+            Expr::Call(callee, paren, arguments) => self.call(callee, paren, arguments),
+        }
+    }
+
+    // A chain of operators that doesn't fit on the line continues on the next
+    // ones, each continuation line starting with an operator.
+    fn operators(&mut self, expression: &Expr) {
+        let mut operands = Vec::new();
+        let mut first = expression;
+        while let Expr::Binary(left, operator, right) | Expr::Logical(left, operator, right) = first
+        {
+            operands.push((operator, right.as_ref()));
+            first = left;
+        }
+        operands.reverse();
+
+        // leave room for a closing ';', a flat rendering always fits
+        let fits = self.width == usize::MAX
+            || self.column() + flat(expression).chars().count() < self.width;
+        self.expression(first);
+        self.depth += 1;
+        for (operator, operand) in operands {
+            let width = operator.lexeme.chars().count() + flat(operand).chars().count() + 2;
+            if self.comments_before(operator) || !fits && self.column() + width >= self.width {
+                self.write("\n");
+                self.indent();
+            } else {
+                self.write(" ");
+            }
+            self.write(&operator.lexeme);
+            if self.comments_before(operand.first_token()) {
+                self.write("\n");
+                self.indent();
+            } else {
+                self.write(" ");
+            }
+            self.expression(operand);
+        }
+        self.depth -= 1;
+    }
+
+    // Arguments that don't fit on the line, or that have comments between
+    // them, are written one per line.
+    fn call(&mut self, callee: &Expr, paren: &Token, arguments: &[Expr]) {
+        self.expression(callee);
+        let flat = arguments.iter().map(flat).collect::<Vec<_>>().join(", ");
+        let positions = &self.positions;
+        let commented = self.comments.peek().is_some_and(|comment| {
+            positions
+                .get(comment.next_token)
+                .is_some_and(|next| *next <= (paren.line, paren.column))
+        });
+        // leave room for the parentheses and a closing ';'
+        if !commented
+            && (arguments.is_empty() || self.column() + flat.chars().count() + 3 <= self.width)
+        {
+            self.write(&format!("({flat})"));
+            return;
+        }
+
+        self.write("(");
+        self.depth += 1;
+        self.comments_before(arguments.first().map_or(paren, Expr::first_token));
+        self.write("\n");
+        for (index, argument) in arguments.iter().enumerate() {
+            self.indent();
+            self.expression(argument);
+            match arguments.get(index + 1) {
+                Some(next) => {
+                    self.write(",");
+                    self.comments_before(next.first_token());
+                }
+                None => {
+                    self.comments_before(paren);
+                }
+            }
+            self.write("\n");
+        }
+        self.depth -= 1;
+        self.indent();
+        self.write(")");
+    }
+}
+
+// Renders an expression on a single line.
+fn flat(expression: &Expr) -> String {
+    let mut formatter = Formatter::new(&[], Vec::new(), usize::MAX);
+    formatter.expression(expression);
+    formatter.output
+}
+
+// Whether the tokens spell the same program, ignoring layout and comments.
+fn same_tokens(left: &[Token], right: &[Token]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .all(|(left, right)| left.lexeme == right.lexeme)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_statements_canonically() {
+        let source = "fun f(a,b){\nvar x=a+b;\n\n\n// why\nreturn x;}\nprint f(1,2);   // three\n";
+        let expected = "\
+fun f(a, b) {
+    var x = a + b;
+
+    // why
+    return x;
+}
+print f(1, 2); // three
+";
+        assert_eq!(format(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn formatting_twice_changes_nothing() {
+        let source = "if (a) { print 1; } else { print 2; }\nwhile (x) x = x - 1;\n";
+        let formatted = format(source).unwrap();
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn wraps_long_operator_chains() {
+        let source = "var total = first_long_operand_name + second_long_operand_name + \
+                      third_long_operand_name + fourth_long_operand_name + fifth;\n";
+        let expected = "\
+var total = first_long_operand_name + second_long_operand_name + third_long_operand_name
+    + fourth_long_operand_name + fifth;
+";
+        assert_eq!(format(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn wraps_long_logical_conditions() {
+        let source = "if (is_ready and has_permission_to_continue_with_the_operation or \
+                      override_everything_because_we_can) print 1;\n";
+        let expected = "\
+if (is_ready and has_permission_to_continue_with_the_operation
+    or override_everything_because_we_can) print 1;
+";
+        assert_eq!(format(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn wraps_long_call_arguments() {
+        let source = "print some_function_with_a_long_name(first_argument_value, \
+                      second_argument_value, third_argument_value);\n";
+        let expected = "\
+print some_function_with_a_long_name(
+    first_argument_value,
+    second_argument_value,
+    third_argument_value
+);
+";
+        assert_eq!(format(source).unwrap(), expected);
+    }
+
+    #[test]
+    fn keeps_a_comment_before_else() {
+        let source = "if (a) { print 1; } // why\nelse print 2;\n";
+        let expected = "\
+if (a) {
+    print 1;
+} // why
+else print 2;
+";
+        assert_eq!(format(source).unwrap(), expected);
+        assert_eq!(format(expected).unwrap(), expected);
+    }
+
+    #[test]
+    fn keeps_comments_between_arguments() {
+        let source = "var x = f(1, // one\n  2);\n";
+        let expected = "\
+var x = f(
+    1, // one
+    2
+);
+";
+        assert_eq!(format(source).unwrap(), expected);
+        assert_eq!(format(expected).unwrap(), expected);
+    }
+
+    #[test]
+    fn keeps_comments_inside_headers() {
+        let source = "if (a // why\n  and b) print 1;\n\
+                      for (var i = 0; // from zero\ni < 3; i = i + 1) print i;\n";
+        let expected = "\
+if (a // why
+    and b) print 1;
+for (var i = 0; // from zero
+    i < 3; i = i + 1) print i;
+";
+        assert_eq!(format(source).unwrap(), expected);
+        assert_eq!(format(expected).unwrap(), expected);
+    }
+
+    #[test]
+    fn keeps_comments_between_statements() {
+        let source = "/* header */\nvar a = 1; // one\n{ // block\n    print a;\n}\n";
+        assert_eq!(format(source).unwrap(), source);
+    }
+}
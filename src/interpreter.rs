@@ -1,39 +1,205 @@
 use crate::environment::Environment;
 use crate::expression::Expr;
-use crate::statement::Stmt;
+use crate::natives;
+use crate::statement::{Stmt, StmtKind};
 use crate::token::Token;
 use crate::token::TokenType;
 use crate::value::{Callable, Return};
 use crate::value::{LoxError, LoxValue};
 use std::cell::RefCell;
+use std::io::{BufRead, Read, Write};
 use std::rc::Rc;
-use std::time::UNIX_EPOCH;
+
+// Deepest call stack, the interpreter recurses for every Lox call.
+const MAX_FRAMES: usize = 1000;
 
 pub struct Interpreter {
     pub global: Rc<RefCell<Environment>>,
     environment: Rc<RefCell<Environment>>,
+    frames: Vec<Frame>,
+    hook: Option<Box<dyn Hook>>,
+    // where `print` writes, stdout unless a tool captures it
+    output: Box<dyn Write>,
+    // where `input` and `read_all` read, stdin unless a tool provides it
+    input: Option<Box<dyn BufRead>>,
+    // the command line arguments following the script
+    args: Vec<String>,
+}
+
+// A function being run, innermost last on the call stack.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub name: String,
+    // line the function is defined on, 0 for the script itself
+    pub definition_line: usize,
+    // line and innermost scope of the statement currently running in this function
+    pub line: usize,
+    pub environment: Rc<RefCell<Environment>>,
+}
+
+// A point where execution goes one of two ways.
+#[derive(Clone, Copy)]
+pub enum Branch<'a> {
+    // an if, while or for statement, taken when its condition holds
+    Statement(&'a Stmt),
+    // an `and` or `or`, taken when the right operand is evaluated
+    Logical(&'a Expr),
+}
+
+// Lets tools such as the debugger observe and pause the program. The hook is
+// not called for code it runs itself through the interpreter it is given.
+pub trait Hook {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        statement: &Stmt,
+    ) -> Result<(), LoxError>;
+
+    // Called with the call stack just after a function's frame is pushed, and
+    // just before it is popped.
+    fn enter_function(&mut self, _frames: &[Frame]) {}
+
+    fn exit_function(&mut self, _frames: &[Frame]) {}
+
+    fn branch(&mut self, _branch: Branch, _taken: bool) {}
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Interpreter {
         let global = Environment::new();
-        let func = |_: &mut Interpreter, _: &[LoxValue]| -> Result<LoxValue, LoxError> {
-            println!(
-                "{:#?}",
-                std::time::SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .expect("error")
-            );
-            Ok(LoxValue::Nil)
-        };
-        let callable = &LoxValue::Callable(Callable::Native { arity: 0, func });
-        global.borrow_mut().define("clock", callable);
+        natives::define_globals(&mut global.borrow_mut());
 
         let environment = Rc::clone(&global);
         Interpreter {
-            global,
+            global: Rc::clone(&global),
             environment,
+            frames: vec![Frame {
+                name: "<script>".to_string(),
+                definition_line: 0,
+                line: 0,
+                environment: Rc::clone(&global),
+            }],
+            hook: None,
+            output: Box::new(std::io::stdout()),
+            input: None,
+            args: Vec::new(),
+        }
+    }
+
+    pub fn set_hook(&mut self, hook: Box<dyn Hook>) {
+        self.hook = Some(hook);
+    }
+
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        self.output = output;
+    }
+
+    pub fn set_input(&mut self, input: Box<dyn BufRead>) {
+        self.input = Some(input);
+    }
+
+    pub fn set_args(&mut self, args: Vec<String>) {
+        self.args = args;
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    // Writes a line of program output.
+    pub fn print(&mut self, text: &str) -> Result<(), LoxError> {
+        // one write per line, so captured output arrives in whole lines
+        self.output
+            .write_all(format!("{text}\n").as_bytes())
+            .map_err(|error| LoxError::Standard(format!("Error: unable to write output: {error}")))
+    }
+
+    // Writes text without ending the line, for a prompt.
+    pub fn prompt(&mut self, text: &str) -> Result<(), LoxError> {
+        self.output
+            .write_all(text.as_bytes())
+            .and_then(|()| self.output.flush())
+            .map_err(|error| LoxError::Standard(format!("Error: unable to write output: {error}")))
+    }
+
+    // The next line of input without its line ending, or None at the end.
+    pub fn read_line(&mut self) -> Result<Option<String>, LoxError> {
+        let mut line = String::new();
+        // stdin is read through its shared buffer, which the REPL's line
+        // editor also reads through when stdin isn't a terminal
+        let read = match self.input.as_mut() {
+            Some(input) => input.read_line(&mut line),
+            None => std::io::stdin().read_line(&mut line),
+        }
+        .map_err(|error| LoxError::Standard(format!("Error: unable to read input: {error}")))?;
+        if read == 0 {
+            return Ok(None);
         }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+
+    // The rest of the input.
+    pub fn read_all(&mut self) -> Result<String, LoxError> {
+        let mut text = String::new();
+        match self.input.as_mut() {
+            Some(input) => input.read_to_string(&mut text),
+            None => std::io::stdin().read_to_string(&mut text),
+        }
+        .map_err(|error| LoxError::Standard(format!("Error: unable to read input: {error}")))?;
+        Ok(text)
+    }
+
+    pub fn environment(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.environment)
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub fn push_frame(&mut self, name: &str, definition_line: usize) -> Result<(), LoxError> {
+        if self.frames.len() >= MAX_FRAMES {
+            let line = self.frames.last().map_or(0, |frame| frame.line);
+            return Err(LoxError::Standard(format!(
+                "Error in line: {line}, stack overflow."
+            )));
+        }
+        self.frames.push(Frame {
+            name: name.to_string(),
+            definition_line,
+            line: 0,
+            environment: Rc::clone(&self.environment),
+        });
+        if let Some(hook) = self.hook.as_mut() {
+            hook.enter_function(&self.frames);
+        }
+        Ok(())
+    }
+
+    pub fn pop_frame(&mut self) {
+        if let Some(hook) = self.hook.as_mut() {
+            hook.exit_function(&self.frames);
+        }
+        self.frames.pop();
+    }
+
+    fn branch(&mut self, branch: Branch, taken: bool) -> bool {
+        if let Some(hook) = self.hook.as_mut() {
+            hook.branch(branch, taken);
+        }
+        taken
     }
 
     pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), LoxError> {
@@ -44,60 +210,88 @@ impl Interpreter {
     }
 
     pub fn interpret_statement(&mut self, statement: &Stmt) -> Result<(), LoxError> {
-        match statement {
-            Stmt::Print(expression) => {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.line = statement.line;
+            frame.environment = Rc::clone(&self.environment);
+        }
+        if let Some(mut hook) = self.hook.take() {
+            let result = hook.before_statement(self, statement);
+            self.hook = Some(hook);
+            result?;
+        }
+
+        match &statement.kind {
+            StmtKind::Print(expression) => {
                 let value = self.interpret_expression(expression)?;
-                println!("{}", value);
-                Ok(())
+                self.print(&value.to_string())
             }
-            Stmt::Expr(expression) => self.interpret_expression(expression).map(|_| {}),
-            Stmt::Var(
+            StmtKind::Expr(expression) => self.interpret_expression(expression).map(|_| {}),
+            StmtKind::Var(
                 Token {
                     token_type: TokenType::Identifier(name),
                     ..
                 },
                 expression,
             ) => {
-                if let Some(expression) = expression {
-                    let value = self.interpret_expression(expression)?;
-                    self.environment.borrow_mut().define(name, &value);
-                } else {
-                    self.environment.borrow_mut().define(name, &LoxValue::Nil);
-                }
-                Ok(())
+                let value = match expression {
+                    Some(expression) => self.interpret_expression(expression)?,
+                    None => LoxValue::Nil,
+                };
+                self.environment.borrow_mut().define(name, &value)
             }
-            Stmt::Block(statements) => {
+            StmtKind::Const(
+                Token {
+                    token_type: TokenType::Identifier(name),
+                    ..
+                },
+                expression,
+            ) => {
+                let value = self.interpret_expression(expression)?;
+                self.environment.borrow_mut().define_constant(name, &value)
+            }
+            StmtKind::Block(statements) => {
                 let new = Environment::new_enclosed(Rc::clone(&self.environment));
-                self.execute_block(&statements, new)
+                self.execute_block(statements, new)
             }
-            Stmt::If(condition, then_block, else_block) => {
-                if self.interpret_expression(condition)?.is_truthy() {
+            StmtKind::If(condition, then_block, else_block) => {
+                let taken = self.interpret_expression(condition)?.is_truthy();
+                if self.branch(Branch::Statement(statement), taken) {
                     self.interpret_statement(then_block)?;
                 } else if let Some(else_block) = else_block {
                     self.interpret_statement(else_block)?;
                 }
                 Ok(())
             }
-            Stmt::While(condition, body) => {
-                while self.interpret_expression(condition)?.is_truthy() {
-                    self.interpret_statement(body)?;
-                }
-                Ok(())
+            StmtKind::While(condition, body) => self.interpret_while(statement, condition, body),
+            StmtKind::For(initializer, condition, increment, body) => {
+                let new = Environment::new_enclosed(Rc::clone(&self.environment));
+                let old = std::mem::replace(&mut self.environment, new);
+                let result = self.interpret_for(
+                    statement,
+                    initializer.as_deref(),
+                    condition.as_ref(),
+                    increment.as_ref(),
+                    body,
+                );
+                self.environment = old;
+                result
             }
-            Stmt::Function(func_stmt) => {
+            StmtKind::Function(func_stmt) => {
                 if let TokenType::Identifier(name) = &func_stmt.name.token_type {
                     let func = LoxValue::Callable(Callable::Function {
                         arity: func_stmt.params.len(),
-                        func_stmt: func_stmt.clone(),
+                        func_stmt: Rc::new(func_stmt.clone()),
                         environment: Rc::clone(&self.environment),
                     });
-                    self.environment.borrow_mut().define(name, &func);
-                    Ok(())
+                    self.environment.borrow_mut().define(name, &func)
                 } else {
-                    panic!("Compiler bug. Unexpected token type: {:?}", &func_stmt.name);
+                    Err(LoxError::Standard(format!(
+                        "Interpreter bug: unexpected function name: {:?}",
+                        func_stmt.name
+                    )))
                 }
             }
-            Stmt::Ret(expr) => {
+            StmtKind::Ret(expr) => {
                 let mut value: Option<LoxValue> = None;
                 if let Some(expr) = expr {
                     value = Some(self.interpret_expression(expr)?);
@@ -105,17 +299,15 @@ impl Interpreter {
                 // oh no so ugly!!
                 Err(LoxError::Return(Return { value }))
             }
-            statement => panic!("Interpreter bug. Unexpected statement: {:?}", statement),
+            kind @ (StmtKind::Var(..) | StmtKind::Const(..)) => Err(LoxError::Standard(format!(
+                "Interpreter bug: unexpected statement: {kind:?}"
+            ))),
         }
     }
 
     pub fn interpret_expression(&mut self, expression: &Expr) -> Result<LoxValue, LoxError> {
         match expression {
-            Expr::Number(number) => Ok(LoxValue::Number(*number)),
-            Expr::String(string) => Ok(LoxValue::String(string.to_string())),
-            Expr::True => Ok(LoxValue::Bool(true)),
-            Expr::False => Ok(LoxValue::Bool(false)),
-            Expr::Nil => Ok(LoxValue::Nil),
+            Expr::Literal(token) => Interpreter::interpret_literal(token),
             Expr::Grouping(expression) => self.interpret_expression(expression),
             Expr::Unary(
                 Token {
@@ -130,19 +322,25 @@ impl Interpreter {
             Expr::Unary(
                 Token {
                     token_type: TokenType::Minus,
+                    line,
                     ..
                 },
                 expression,
             ) => match self.interpret_expression(expression)? {
                 LoxValue::Number(number) => Ok(LoxValue::Number(-number)),
-                _ => Err(LoxError::Standard(
-                    "Error: operand must be a number.".to_string(),
-                )),
+                LoxValue::Integer(integer) => {
+                    integer.checked_neg().map(LoxValue::Integer).ok_or_else(|| {
+                        LoxError::Standard(format!("Error in line: {line}, integer overflow."))
+                    })
+                }
+                _ => Err(LoxError::Standard(format!(
+                    "Error in line: {line}, operand must be a number."
+                ))),
             },
             Expr::Binary(left, token, right) => {
                 self.interpret_binary_expression(left, token, right)
             }
-            Expr::Variable(token) => self.environment.borrow().get(&token),
+            Expr::Variable(token) => self.environment.borrow().get(token),
             Expr::Assignment(
                 Token {
                     token_type: TokenType::Identifier(name),
@@ -163,10 +361,10 @@ impl Interpreter {
                 right,
             ) => {
                 let left = self.interpret_expression(left)?;
-                if left.is_truthy() {
-                    Ok(left)
-                } else {
+                if self.branch(Branch::Logical(expression), !left.is_truthy()) {
                     self.interpret_expression(right)
+                } else {
+                    Ok(left)
                 }
             }
             Expr::Logical(
@@ -178,37 +376,114 @@ impl Interpreter {
                 right,
             ) => {
                 let left = self.interpret_expression(left)?;
-                if left.is_truthy() {
+                if self.branch(Branch::Logical(expression), left.is_truthy()) {
                     self.interpret_expression(right)
                 } else {
                     Ok(left)
                 }
             }
-            Expr::Call(callee, _, arguments) => {
-                let callee = self.interpret_expression(callee)?;
-                let arguments: Result<Vec<LoxValue>, LoxError> = arguments
-                    .iter()
-                    .map(|argument| self.interpret_expression(argument))
-                    .collect();
-                let arguments = arguments?;
-                if let LoxValue::Callable(function) = callee {
-                    if function.arity() == arguments.len() {
-                        function.call(self, &arguments)
-                    } else {
-                        Err(LoxError::Standard(format!(
-                            "Expected {} arguments but got {} .",
-                            function.arity(),
-                            arguments.len()
-                        )))
-                    }
+            Expr::Conditional(condition, then_branch, else_branch) => {
+                if self.interpret_expression(condition)?.is_truthy() {
+                    self.interpret_expression(then_branch)
                 } else {
-                    Err(LoxError::Standard(
-                        "Can only call functions and classes".to_string(),
-                    ))
+                    self.interpret_expression(else_branch)
                 }
             }
+            Expr::Call(callee, paren, arguments) => {
+                self.interpret_call_expression(callee, paren, arguments)
+            }
 
-            expression => panic!("Interpreter bug: unexpected expression: {:?}", expression),
+            expression => Err(LoxError::Standard(format!(
+                "Interpreter bug: unexpected expression: {expression:?}"
+            ))),
+        }
+    }
+
+    fn interpret_literal(token: &Token) -> Result<LoxValue, LoxError> {
+        Ok(match &token.token_type {
+            TokenType::Number(number) => LoxValue::Number(*number),
+            TokenType::Integer(integer) => LoxValue::Integer(*integer),
+            TokenType::String(string) => LoxValue::String(string.clone()),
+            TokenType::True => LoxValue::Bool(true),
+            TokenType::False => LoxValue::Bool(false),
+            TokenType::Nil => LoxValue::Nil,
+            token_type => {
+                return Err(LoxError::Standard(format!(
+                    "Interpreter bug: unexpected literal: {token_type:?}"
+                )))
+            }
+        })
+    }
+
+    fn interpret_while(
+        &mut self,
+        statement: &Stmt,
+        condition: &Expr,
+        body: &Stmt,
+    ) -> Result<(), LoxError> {
+        loop {
+            let taken = self.interpret_expression(condition)?.is_truthy();
+            if !self.branch(Branch::Statement(statement), taken) {
+                return Ok(());
+            }
+            self.interpret_statement(body)?;
+        }
+    }
+
+    fn interpret_for(
+        &mut self,
+        statement: &Stmt,
+        initializer: Option<&Stmt>,
+        condition: Option<&Expr>,
+        increment: Option<&Expr>,
+        body: &Stmt,
+    ) -> Result<(), LoxError> {
+        if let Some(initializer) = initializer {
+            self.interpret_statement(initializer)?;
+        }
+        while match condition {
+            Some(condition) => {
+                let taken = self.interpret_expression(condition)?.is_truthy();
+                self.branch(Branch::Statement(statement), taken)
+            }
+            None => true,
+        } {
+            self.interpret_statement(body)?;
+            if let Some(increment) = increment {
+                self.interpret_expression(increment)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn interpret_call_expression(
+        &mut self,
+        callee: &Expr,
+        paren: &Token,
+        arguments: &[Expr],
+    ) -> Result<LoxValue, LoxError> {
+        let callee = self.interpret_expression(callee)?;
+        let arguments: Result<Vec<LoxValue>, LoxError> = arguments
+            .iter()
+            .map(|argument| self.interpret_expression(argument))
+            .collect();
+        let arguments = arguments?;
+        if let LoxValue::Callable(function) = callee {
+            if function.arity() == arguments.len() {
+                function.call(self, &arguments)
+            } else {
+                Err(LoxError::Standard(format!(
+                    "Error in line: {}, expected {} arguments but got {}.",
+                    paren.line,
+                    function.arity(),
+                    arguments.len()
+                )))
+            }
+        } else {
+            Err(LoxError::Standard(format!(
+                "Error in line: {}, can only call functions and classes.",
+                paren.line
+            )))
         }
     }
 
@@ -218,55 +493,97 @@ impl Interpreter {
         token: &Token,
         right: &Expr,
     ) -> Result<LoxValue, LoxError> {
-        let Token { token_type, line } = token;
-        match (self.interpret_expression(left)?, token_type, self.interpret_expression(right)?) {
-            (LoxValue::Number(left), TokenType::Plus, LoxValue::Number(right)) => {
-                Ok(LoxValue::Number(left + right))
-            }
+        let Token {
+            token_type, line, ..
+        } = token;
+        match (
+            self.interpret_expression(left)?,
+            token_type,
+            self.interpret_expression(right)?,
+        ) {
+            (left, TokenType::EqualEqual, right) => Ok(LoxValue::Bool(left == right)),
+            (left, TokenType::BangEqual, right) => Ok(LoxValue::Bool(left != right)),
             (LoxValue::String(left), TokenType::Plus, LoxValue::String(right)) => {
                 Ok(LoxValue::String(left + &right))
             }
-            (LoxValue::Number(left), TokenType::Minus, LoxValue::Number(right)) => {
-                Ok(LoxValue::Number(left - right))
-            }
-            (LoxValue::Number(left), TokenType::Slash, LoxValue::Number(right)) => {
-                Ok(LoxValue::Number(left / right))
-            }
-            (LoxValue::Number(left), TokenType::Star, LoxValue::Number(right)) => {
-                Ok(LoxValue::Number(left * right))
-            }
-            (LoxValue::Number(left), TokenType::Less, LoxValue::Number(right)) => {
+            // strings order by code point, which is how UTF-8 bytes compare
+            (LoxValue::String(left), TokenType::Less, LoxValue::String(right)) => {
                 Ok(LoxValue::Bool(left < right))
             }
-            (LoxValue::Number(left), TokenType::LessEqual, LoxValue::Number(right)) => {
+            (LoxValue::String(left), TokenType::LessEqual, LoxValue::String(right)) => {
                 Ok(LoxValue::Bool(left <= right))
             }
-            (LoxValue::Number(left), TokenType::Greater, LoxValue::Number(right)) => {
+            (LoxValue::String(left), TokenType::Greater, LoxValue::String(right)) => {
                 Ok(LoxValue::Bool(left > right))
             }
-            (LoxValue::Number(left), TokenType::GreaterEqual, LoxValue::Number(right)) => {
+            (LoxValue::String(left), TokenType::GreaterEqual, LoxValue::String(right)) => {
                 Ok(LoxValue::Bool(left >= right))
             }
-            (left, TokenType::EqualEqual, right) => Ok(LoxValue::Bool(left == right)),
-            (left, TokenType::BangEqual, right) => Ok(LoxValue::Bool(left != right)),
-            (_, TokenType::Minus, _)
-            | (_, TokenType::Star, _)
-            | (_, TokenType::Slash, _)
-            | (_, TokenType::Less, _)
-            | (_, TokenType::LessEqual, _)
-            | (_, TokenType::Greater, _)
-            | (_, TokenType::GreaterEqual, _) => Err(LoxError::Standard(format!(
-                "Error in line: {}, operands must both be numbers.",
-                line
-            ))),
-            (_, TokenType::Plus, _) => Err(LoxError::Standard(format!(
-                "Error in line: {}, operands must both be numbers or strings.",
-                line
-            ))),
-            (left, op, right) => panic!("Interpreter bug: Unexpected match of left expression: {:?}, operation: {:?}, right expression: {:?}", left, op, right),
+            // integer division always produces a float, so it is handled below
+            (LoxValue::Integer(left), op, LoxValue::Integer(right)) if *op != TokenType::Slash => {
+                Interpreter::interpret_integer_operation(left, op, right, *line)
+            }
+            (left, op, right) => match (left.as_float(), right.as_float()) {
+                (Some(left), Some(right)) => {
+                    Interpreter::interpret_float_operation(left, op, right)
+                }
+                _ if *op == TokenType::Plus => Err(LoxError::Standard(format!(
+                    "Error in line: {line}, operands must both be numbers or strings."
+                ))),
+                _ => Err(LoxError::Standard(format!(
+                    "Error in line: {line}, operands must both be numbers."
+                ))),
+            },
         }
     }
 
+    fn interpret_integer_operation(
+        left: i64,
+        op: &TokenType,
+        right: i64,
+        line: usize,
+    ) -> Result<LoxValue, LoxError> {
+        let result = match op {
+            TokenType::Plus => left.checked_add(right),
+            TokenType::Minus => left.checked_sub(right),
+            TokenType::Star => left.checked_mul(right),
+            TokenType::Less => return Ok(LoxValue::Bool(left < right)),
+            TokenType::LessEqual => return Ok(LoxValue::Bool(left <= right)),
+            TokenType::Greater => return Ok(LoxValue::Bool(left > right)),
+            TokenType::GreaterEqual => return Ok(LoxValue::Bool(left >= right)),
+            op => {
+                return Err(LoxError::Standard(format!(
+                    "Interpreter bug: unexpected integer operation: {op:?}"
+                )))
+            }
+        };
+        result
+            .map(LoxValue::Integer)
+            .ok_or_else(|| LoxError::Standard(format!("Error in line: {line}, integer overflow.")))
+    }
+
+    fn interpret_float_operation(
+        left: f64,
+        op: &TokenType,
+        right: f64,
+    ) -> Result<LoxValue, LoxError> {
+        Ok(match op {
+            TokenType::Plus => LoxValue::Number(left + right),
+            TokenType::Minus => LoxValue::Number(left - right),
+            TokenType::Star => LoxValue::Number(left * right),
+            TokenType::Slash => LoxValue::Number(left / right),
+            TokenType::Less => LoxValue::Bool(left < right),
+            TokenType::LessEqual => LoxValue::Bool(left <= right),
+            TokenType::Greater => LoxValue::Bool(left > right),
+            TokenType::GreaterEqual => LoxValue::Bool(left >= right),
+            op => {
+                return Err(LoxError::Standard(format!(
+                    "Interpreter bug: unexpected float operation: {op:?}"
+                )))
+            }
+        })
+    }
+
     pub fn execute_block(
         &mut self,
         statements: &[Stmt],
@@ -284,3 +601,24 @@ impl Interpreter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+
+    // Runs the program without printing anything, watched by the hook that
+    // `hook` makes from its statements.
+    pub(crate) fn run_with_hook(source: &str, hook: impl FnOnce(&[Stmt]) -> Box<dyn Hook>) {
+        let statements = Scanner::scan(source)
+            .and_then(|tokens| Parser::parse(&tokens))
+            .unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.set_output(Box::new(std::io::sink()));
+        interpreter.set_hook(hook(&statements));
+        Resolver::resolve(&statements, &interpreter.global.borrow()).unwrap();
+        interpreter.interpret(&statements).unwrap();
+    }
+}
@@ -0,0 +1,29 @@
+#![warn(clippy::all)]
+#![warn(clippy::pedantic)]
+// The interpreter and its tools, used by the rlox binary and the fuzz targets.
+// It is not a published API, so the documentation lints for one don't apply.
+#![allow(
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::must_use_candidate
+)]
+
+pub mod ast_printer;
+pub mod coverage;
+pub mod dap;
+pub mod debugger;
+pub mod environment;
+pub mod expression;
+pub mod formatter;
+pub mod interpreter;
+pub mod linter;
+pub mod lsp;
+pub mod natives;
+pub mod parser;
+pub mod profiler;
+pub mod resolver;
+pub mod scanner;
+pub mod statement;
+pub mod test_runner;
+pub mod token;
+pub mod value;
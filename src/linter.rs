@@ -0,0 +1,541 @@
+use crate::expression::Expr;
+use crate::natives;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::statement::{FuncStmt, Stmt, StmtKind};
+use crate::token::{Comment, Token, TokenType};
+use crate::value::LoxError;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// Name of the file listing lints to disable, looked up from the linted file's
+// directory upwards. It holds one lint code or name per line, `#` starts a comment.
+pub const CONFIG_FILE: &str = ".rloxlint";
+
+// Marker of an inline comment disabling lints on its own line and the next,
+// e.g. `// lint: allow W001, shadowed-variable`.
+const INLINE_ALLOW: &str = "lint: allow";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Lint {
+    UnusedVariable,
+    UnusedParameter,
+    UnreachableCode,
+    ShadowedVariable,
+    AssignmentInCondition,
+    WrongArity,
+    SelfComparison,
+}
+
+impl Lint {
+    const ALL: [Lint; 7] = [
+        Lint::UnusedVariable,
+        Lint::UnusedParameter,
+        Lint::UnreachableCode,
+        Lint::ShadowedVariable,
+        Lint::AssignmentInCondition,
+        Lint::WrongArity,
+        Lint::SelfComparison,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "W001",
+            Lint::UnusedParameter => "W002",
+            Lint::UnreachableCode => "W003",
+            Lint::ShadowedVariable => "W004",
+            Lint::AssignmentInCondition => "W005",
+            Lint::WrongArity => "W006",
+            Lint::SelfComparison => "W007",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariable => "unused-variable",
+            Lint::UnusedParameter => "unused-parameter",
+            Lint::UnreachableCode => "unreachable-code",
+            Lint::ShadowedVariable => "shadowed-variable",
+            Lint::AssignmentInCondition => "assignment-in-condition",
+            Lint::WrongArity => "wrong-arity",
+            Lint::SelfComparison => "self-comparison",
+        }
+    }
+
+    // Accepts either the code or the name of a lint.
+    pub fn find(text: &str) -> Option<Lint> {
+        Lint::ALL
+            .iter()
+            .copied()
+            .find(|lint| lint.code().eq_ignore_ascii_case(text) || lint.name() == text)
+    }
+}
+
+#[derive(Debug)]
+pub struct Warning {
+    pub lint: Lint,
+    pub line: usize,
+    pub message: String,
+}
+
+// Reads the lints disabled by the nearest config file, if there is one.
+pub fn disabled_lints(path: &Path) -> Result<Vec<Lint>, LoxError> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let config = path
+        .ancestors()
+        .skip(1)
+        .map(|directory| directory.join(CONFIG_FILE))
+        .find(|config| config.is_file());
+    let Some(config) = config else {
+        return Ok(Vec::new());
+    };
+    let contents = std::fs::read_to_string(&config).map_err(|error| {
+        LoxError::Standard(format!("unable to read '{}': {}", config.display(), error))
+    })?;
+
+    let mut lints = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let entry = line.split('#').next().unwrap_or_default().trim();
+        if entry.is_empty() {
+            continue;
+        }
+        match Lint::find(entry) {
+            Some(lint) => lints.push(lint),
+            None => {
+                return Err(LoxError::Standard(format!(
+                    "unknown lint '{}' in '{}' at line {}",
+                    entry,
+                    config.display(),
+                    number + 1
+                )))
+            }
+        }
+    }
+    Ok(lints)
+}
+
+pub fn lint(source: &str, disabled: &[Lint]) -> Result<Vec<Warning>, Vec<LoxError>> {
+    let (tokens, comments) = Scanner::scan_with_comments(source)?;
+    let statements = Parser::parse(&tokens)?;
+
+    let mut linter = Linter::new();
+    linter.statements(&statements);
+    linter.end_scope();
+
+    let allowed = inline_allows(&comments);
+    let mut warnings: Vec<Warning> = linter
+        .warnings
+        .into_iter()
+        .filter(|warning| {
+            !disabled.contains(&warning.lint) && !allowed.contains(&(warning.line, warning.lint))
+        })
+        .collect();
+    warnings.sort_by_key(|warning| warning.line);
+    Ok(warnings)
+}
+
+// Lines on which lints were disabled by a comment.
+fn inline_allows(comments: &[Comment]) -> Vec<(usize, Lint)> {
+    let mut allowed = Vec::new();
+    for comment in comments {
+        let Some((_, lints)) = comment.text.split_once(INLINE_ALLOW) else {
+            continue;
+        };
+        let lints = lints
+            .trim_end_matches("*/")
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter_map(Lint::find);
+        for lint in lints {
+            allowed.push((comment.line, lint));
+            allowed.push((comment.line + 1, lint));
+        }
+    }
+    allowed
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum BindingKind {
+    Variable,
+    Constant,
+    Parameter,
+    // arity is forgotten once the name is reassigned
+    Function(Option<usize>),
+}
+
+struct Binding {
+    kind: BindingKind,
+    line: usize,
+    used: bool,
+}
+
+struct Linter {
+    // the first scope holds the script's globals
+    scopes: Vec<HashMap<String, Binding>>,
+    // globals may be used by functions declared before them, so uses of
+    // names not found in a local scope are only matched up at the end
+    global_uses: HashSet<String>,
+    natives: HashMap<String, usize>,
+    warnings: Vec<Warning>,
+}
+
+impl Linter {
+    fn new() -> Linter {
+        Linter {
+            scopes: vec![HashMap::new()],
+            global_uses: HashSet::new(),
+            natives: natives::arities(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, lint: Lint, at: usize, message: String) {
+        self.warnings.push(Warning {
+            lint,
+            line: at,
+            message,
+        });
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        let is_global = self.scopes.len() == 1;
+        let Some(scope) = self.scopes.pop() else {
+            return;
+        };
+        let mut unused: Vec<_> = scope
+            .into_iter()
+            .filter(|(name, binding)| {
+                let used = binding.used || (is_global && self.global_uses.contains(name));
+                !used && !name.starts_with('_')
+            })
+            .collect();
+        unused.sort_by_key(|(_, binding)| binding.line);
+        for (name, binding) in unused {
+            match binding.kind {
+                BindingKind::Variable | BindingKind::Constant => self.warn(
+                    Lint::UnusedVariable,
+                    binding.line,
+                    format!("variable '{name}' is never used"),
+                ),
+                BindingKind::Parameter => self.warn(
+                    Lint::UnusedParameter,
+                    binding.line,
+                    format!("parameter '{name}' is never used"),
+                ),
+                BindingKind::Function(_) => {}
+            }
+        }
+    }
+
+    fn declare(&mut self, token: &Token, kind: BindingKind) {
+        let name = token.identifier();
+        let outer = self.scopes[..self.scopes.len() - 1]
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name));
+        if let Some(outer) = outer {
+            let message = format!("'{}' shadows the declaration at line {}", name, outer.line);
+            self.warn(Lint::ShadowedVariable, token.line, message);
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(
+                name.to_string(),
+                Binding {
+                    kind,
+                    line: token.line,
+                    used: false,
+                },
+            );
+        }
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<&mut Binding> {
+        let local = self
+            .scopes
+            .iter()
+            .skip(1)
+            .rposition(|scope| scope.contains_key(name));
+        let index = if let Some(index) = local {
+            index + 1
+        } else {
+            self.global_uses.insert(name.to_string());
+            0
+        };
+        self.scopes[index].get_mut(name)
+    }
+
+    fn known_arity(&mut self, name: &str) -> Option<usize> {
+        match self.lookup(name) {
+            Some(binding) => match binding.kind {
+                BindingKind::Function(arity) => arity,
+                _ => None,
+            },
+            None => self.natives.get(name).copied(),
+        }
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        let mut returned = false;
+        for statement in statements {
+            if returned {
+                self.warn(
+                    Lint::UnreachableCode,
+                    statement.line,
+                    "unreachable code after return".to_string(),
+                );
+                returned = false;
+            } else {
+                returned = always_returns(statement);
+            }
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Stmt) {
+        match &statement.kind {
+            StmtKind::Block(statements) => {
+                self.begin_scope();
+                self.statements(statements);
+                self.end_scope();
+            }
+            StmtKind::Expr(expression) | StmtKind::Print(expression) => {
+                self.expression(expression);
+            }
+            StmtKind::Function(func_stmt) => self.function(func_stmt),
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.condition(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            StmtKind::Var(name, initializer) => {
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                self.declare(name, BindingKind::Variable);
+            }
+            StmtKind::Const(name, initializer) => {
+                self.expression(initializer);
+                self.declare(name, BindingKind::Constant);
+            }
+            StmtKind::While(condition, body) => {
+                self.condition(condition);
+                self.statement(body);
+            }
+            StmtKind::For(initializer, condition, increment, body) => {
+                self.begin_scope();
+                if let Some(initializer) = initializer {
+                    self.statement(initializer);
+                }
+                if let Some(condition) = condition {
+                    self.condition(condition);
+                }
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                }
+                self.statement(body);
+                self.end_scope();
+            }
+            StmtKind::Ret(value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+        }
+    }
+
+    fn function(&mut self, func_stmt: &FuncStmt) {
+        let arity = func_stmt.params.len();
+        self.declare(&func_stmt.name, BindingKind::Function(Some(arity)));
+        self.begin_scope();
+        for param in &func_stmt.params {
+            self.declare(param, BindingKind::Parameter);
+        }
+        self.statements(&func_stmt.body);
+        self.end_scope();
+    }
+
+    fn condition(&mut self, condition: &Expr) {
+        // wrapping the assignment in parentheses marks it as intended
+        if let Expr::Assignment(name, _) = condition {
+            self.warn(
+                Lint::AssignmentInCondition,
+                name.line,
+                format!("assignment to '{}' used as a condition", name.identifier()),
+            );
+        }
+        self.expression(condition);
+    }
+
+    fn expression(&mut self, expression: &Expr) {
+        match expression {
+            Expr::Literal(_) => {}
+            Expr::Variable(name) => {
+                if let Some(binding) = self.lookup(name.identifier()) {
+                    binding.used = true;
+                }
+            }
+            Expr::Assignment(name, value) => {
+                self.expression(value);
+                let binding = self.lookup(name.identifier());
+                if let Some(binding) = binding {
+                    if let BindingKind::Function(_) = binding.kind {
+                        binding.kind = BindingKind::Function(None);
+                    }
+                }
+            }
+            Expr::Grouping(expression) | Expr::Unary(_, expression) => {
+                self.expression(expression);
+            }
+            Expr::Binary(left, operator, right) => {
+                self.check_self_comparison(left, operator, right);
+                self.expression(left);
+                self.expression(right);
+            }
+            Expr::Logical(left, _, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expr::Conditional(condition, then_branch, else_branch) => {
+                self.condition(condition);
+                self.expression(then_branch);
+                self.expression(else_branch);
+            }
+            Expr::Call(callee, paren, arguments) => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+                if let Expr::Variable(name) = callee.as_ref() {
+                    let name = name.identifier();
+                    match self.known_arity(name) {
+                        Some(arity) if arity != arguments.len() => {
+                            let message = format!(
+                                "'{}' takes {} arguments but is called with {}",
+                                name,
+                                arity,
+                                arguments.len()
+                            );
+                            self.warn(Lint::WrongArity, paren.line, message);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_self_comparison(&mut self, left: &Expr, operator: &Token, right: &Expr) {
+        let is_comparison = matches!(
+            operator.token_type,
+            TokenType::EqualEqual
+                | TokenType::BangEqual
+                | TokenType::Less
+                | TokenType::LessEqual
+                | TokenType::Greater
+                | TokenType::GreaterEqual
+        );
+        if let (Expr::Variable(left), Expr::Variable(right)) = (left, right) {
+            if is_comparison && left.identifier() == right.identifier() {
+                self.warn(
+                    Lint::SelfComparison,
+                    operator.line,
+                    format!("'{}' is compared with itself", left.identifier()),
+                );
+            }
+        }
+    }
+}
+
+// Whether every path through the statement ends in a `return`.
+fn always_returns(statement: &Stmt) -> bool {
+    match &statement.kind {
+        StmtKind::Ret(_) => true,
+        StmtKind::Block(statements) => statements.iter().any(always_returns),
+        StmtKind::If(_, then_branch, Some(else_branch)) => {
+            always_returns(then_branch) && always_returns(else_branch)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The code and line of each warning.
+    fn warnings(source: &str) -> Vec<(&'static str, usize)> {
+        lint(source, &[])
+            .unwrap()
+            .iter()
+            .map(|warning| (warning.lint.code(), warning.line))
+            .collect()
+    }
+
+    #[test]
+    fn warns_about_unused_variables_and_parameters() {
+        let source = "fun f(a, b) {\n  var c = 1;\n  return a;\n}\nf(1, 2);\n";
+        assert_eq!(warnings(source), [("W002", 1), ("W001", 2)]);
+    }
+
+    #[test]
+    fn ignores_names_starting_with_an_underscore() {
+        assert_eq!(warnings("fun f(_a) {\n  var _b = 1;\n}\nf(1);\n"), []);
+    }
+
+    #[test]
+    fn warns_about_code_after_a_return() {
+        let source = "fun f() {\n  return 1;\n  print 2;\n}\nf();\n";
+        assert_eq!(warnings(source), [("W003", 3)]);
+    }
+
+    #[test]
+    fn warns_about_shadowing() {
+        let source = "var a = 1;\n{\n  var a = 2;\n  print a;\n}\nprint a;\n";
+        let found = lint(source, &[]).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].lint, Lint::ShadowedVariable);
+        assert_eq!(found[0].message, "'a' shadows the declaration at line 1");
+    }
+
+    #[test]
+    fn warns_about_assignments_used_as_conditions() {
+        assert_eq!(warnings("var a = 1;\nif (a = 2) print a;\n"), [("W005", 2)]);
+    }
+
+    #[test]
+    fn warns_about_wrong_arity() {
+        let source = "fun f(a) { return a; }\nf(1, 2);\nprint len(\"a\", 1);\n";
+        assert_eq!(warnings(source), [("W006", 2), ("W006", 3)]);
+    }
+
+    #[test]
+    fn warns_about_self_comparisons() {
+        assert_eq!(warnings("var a = 1;\nprint a == a;\n"), [("W007", 2)]);
+    }
+
+    #[test]
+    fn skips_disabled_lints() {
+        let source = "var a = 1;\n{\n  var a = 2;\n}\n";
+        assert_eq!(warnings(source), [("W001", 1), ("W004", 3), ("W001", 3)]);
+        let found = lint(source, &[Lint::UnusedVariable, Lint::ShadowedVariable]).unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn skips_lints_allowed_by_a_comment() {
+        let source = "// lint: allow W001\nvar a = 1;\nvar b = 2; // lint: allow unused-variable\n\nvar c = 3;\n";
+        assert_eq!(warnings(source), [("W001", 5)]);
+    }
+
+    #[test]
+    fn finds_lints_by_code_or_name() {
+        assert_eq!(Lint::find("w004"), Some(Lint::ShadowedVariable));
+        assert_eq!(Lint::find("self-comparison"), Some(Lint::SelfComparison));
+        assert_eq!(Lint::find("no-such-lint"), None);
+    }
+}
@@ -0,0 +1,990 @@
+use crate::environment::Environment;
+use crate::expression::Expr;
+use crate::linter;
+use crate::natives;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
+use crate::statement::{FuncStmt, Stmt, StmtKind};
+use crate::token::{Token, TokenType, KEYWORDS};
+use crate::value::LoxError;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, References, Rename,
+    Request as RequestTrait,
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse, Diagnostic,
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, NumberOrString, OneOf, Position,
+    PublishDiagnosticsParams, Range, ReferenceParams, RenameParams, ServerCapabilities, SymbolKind,
+    TextDocumentPositionParams, TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit, Url,
+    WorkspaceEdit,
+};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+
+type LspResult<T> = Result<T, Box<dyn Error + Sync + Send>>;
+
+// Serves the Language Server Protocol over stdin and stdout until the client
+// shuts the server down.
+pub fn run() -> LspResult<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        rename_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(lsp_types::CompletionOptions::default()),
+        ..ServerCapabilities::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = Server {
+        connection: &connection,
+        documents: HashMap::new(),
+        natives: natives::arities(),
+    };
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    break;
+                }
+                server.request(request)?;
+            }
+            Message::Notification(notification) => server.notification(notification)?,
+            Message::Response(_) => {}
+        }
+    }
+    // the writer thread only finishes once the connection is gone
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+    documents: HashMap<Url, String>,
+    natives: HashMap<String, usize>,
+}
+
+impl Server<'_> {
+    fn notification(&mut self, notification: Notification) -> LspResult<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let Some(params) = notification_params::<DidOpenTextDocumentParams>(notification)
+                else {
+                    return Ok(());
+                };
+                let document = params.text_document;
+                self.documents.insert(document.uri.clone(), document.text);
+                self.publish_diagnostics(&document.uri)?;
+            }
+            DidChangeTextDocument::METHOD => {
+                let Some(params) = notification_params::<DidChangeTextDocumentParams>(notification)
+                else {
+                    return Ok(());
+                };
+                // with full synchronization the last change holds the whole document
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    let uri = params.text_document.uri;
+                    self.documents.insert(uri.clone(), change.text);
+                    self.publish_diagnostics(&uri)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let Some(params) = notification_params::<DidCloseTextDocumentParams>(notification)
+                else {
+                    return Ok(());
+                };
+                self.documents.remove(&params.text_document.uri);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn request(&mut self, request: Request) -> LspResult<()> {
+        let id = request.id.clone();
+        let result = match request.method.as_str() {
+            GotoDefinition::METHOD => params(request).map(|params| self.definition(params)),
+            References::METHOD => params(request).map(|params| self.references(&params)),
+            HoverRequest::METHOD => params(request).map(|params| self.hover(params)),
+            DocumentSymbolRequest::METHOD => {
+                params(request).map(|params| self.document_symbols(&params))
+            }
+            Rename::METHOD => match params(request).and_then(|params| self.rename(params)) {
+                Ok(edit) => Ok(serde_json::to_value(edit)?),
+                Err(message) => Err(message),
+            },
+            Completion::METHOD => params(request).map(|params| self.completion(&params)),
+            _ => {
+                return self.respond(Response::new_err(
+                    id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("unsupported request '{}'", request.method),
+                ))
+            }
+        };
+        // a request the server can't use is refused, the server keeps going
+        let response = match result {
+            Ok(result) => Response::new_ok(id, result),
+            Err(message) => Response::new_err(id, ErrorCode::InvalidParams as i32, message),
+        };
+        self.respond(response)
+    }
+
+    fn respond(&self, response: Response) -> LspResult<()> {
+        self.connection.sender.send(Message::Response(response))?;
+        Ok(())
+    }
+
+    fn publish_diagnostics(&self, uri: &Url) -> LspResult<()> {
+        let Some(source) = self.documents.get(uri) else {
+            return Ok(());
+        };
+        let params = PublishDiagnosticsParams {
+            uri: uri.clone(),
+            diagnostics: diagnostics(uri, source),
+            version: None,
+        };
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))?;
+        Ok(())
+    }
+
+    // The parsed document and the definition under the cursor, if any.
+    fn symbol_at(&self, position: &TextDocumentPositionParams) -> Option<(&str, Index, usize)> {
+        let source = self.documents.get(&position.text_document.uri)?;
+        let index = Index::build(&parse(source)?);
+        let (line, column) = from_position(source, position.position);
+        let definition = index.definition_at(line, column)?;
+        Some((source, index, definition))
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> serde_json::Value {
+        let position = params.text_document_position_params;
+        let response = self
+            .symbol_at(&position)
+            .map(|(source, index, definition)| {
+                let token = &index.definitions[definition].token;
+                GotoDefinitionResponse::Scalar(Location::new(
+                    position.text_document.uri.clone(),
+                    token_range(source, token),
+                ))
+            });
+        serde_json::to_value(response).unwrap_or_default()
+    }
+
+    fn references(&self, params: &ReferenceParams) -> serde_json::Value {
+        let position = &params.text_document_position;
+        let locations = self.symbol_at(position).map(|(source, index, definition)| {
+            let declaration = params
+                .context
+                .include_declaration
+                .then(|| &index.definitions[definition].token);
+            declaration
+                .into_iter()
+                .chain(index.references_to(definition))
+                .map(|token| {
+                    Location::new(
+                        position.text_document.uri.clone(),
+                        token_range(source, token),
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
+        serde_json::to_value(locations).unwrap_or_default()
+    }
+
+    fn hover(&self, params: HoverParams) -> serde_json::Value {
+        let position = params.text_document_position_params;
+        let hover = match self.symbol_at(&position) {
+            Some((source, index, definition)) => {
+                let definition = &index.definitions[definition];
+                Some(Hover {
+                    contents: markdown(definition.describe()),
+                    range: Some(token_range(source, &definition.token)),
+                })
+            }
+            None => self.native_hover(&position),
+        };
+        serde_json::to_value(hover).unwrap_or_default()
+    }
+
+    fn native_hover(&self, position: &TextDocumentPositionParams) -> Option<Hover> {
+        let source = self.documents.get(&position.text_document.uri)?;
+        let tokens = Scanner::scan(source).ok()?;
+        let (line, column) = from_position(source, position.position);
+        let token = tokens.iter().find(|token| covers(token, line, column))?;
+        let arity = self.natives.get(token.identifier())?;
+        Some(Hover {
+            contents: markdown(format!(
+                "```lox\nfun {}/{}\n```\nnative function taking {} arguments",
+                token.identifier(),
+                arity,
+                arity
+            )),
+            range: Some(token_range(source, token)),
+        })
+    }
+
+    fn document_symbols(&self, params: &DocumentSymbolParams) -> serde_json::Value {
+        let symbols = self
+            .documents
+            .get(&params.text_document.uri)
+            .and_then(|source| {
+                let statements = parse(source)?;
+                Some(DocumentSymbolResponse::Nested(document_symbols(
+                    source,
+                    &statements,
+                )))
+            });
+        serde_json::to_value(symbols).unwrap_or_default()
+    }
+
+    fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>, String> {
+        let valid = matches!(
+            Scanner::scan(&params.new_name).as_deref(),
+            Ok([
+                Token {
+                    token_type: TokenType::Identifier(_),
+                    ..
+                },
+                _
+            ])
+        );
+        if !valid {
+            return Err(format!("'{}' is not a valid identifier", params.new_name));
+        }
+
+        let RenameParams {
+            text_document_position: position,
+            new_name,
+            ..
+        } = params;
+        let Some((source, index, definition)) = self.symbol_at(&position) else {
+            return Ok(None);
+        };
+        let edits = std::iter::once(&index.definitions[definition].token)
+            .chain(index.references_to(definition))
+            .map(|token| TextEdit::new(token_range(source, token), new_name.clone()))
+            .collect();
+        let changes = HashMap::from([(position.text_document.uri, edits)]);
+        Ok(Some(WorkspaceEdit::new(changes)))
+    }
+
+    fn completion(&self, params: &CompletionParams) -> serde_json::Value {
+        let uri = &params.text_document_position.text_document.uri;
+        let definitions = self
+            .documents
+            .get(uri)
+            .and_then(|source| parse(source))
+            .map(|statements| Index::build(&statements).definitions)
+            .unwrap_or_default();
+
+        let mut items: Vec<CompletionItem> = KEYWORDS
+            .iter()
+            .map(|keyword| completion_item(keyword, CompletionItemKind::KEYWORD))
+            .chain(
+                self.natives
+                    .keys()
+                    .map(|name| completion_item(name, CompletionItemKind::FUNCTION)),
+            )
+            .chain(definitions.iter().map(|definition| {
+                let kind = match definition.kind {
+                    DefinitionKind::Function { .. } => CompletionItemKind::FUNCTION,
+                    DefinitionKind::Constant => CompletionItemKind::CONSTANT,
+                    DefinitionKind::Variable | DefinitionKind::Parameter => {
+                        CompletionItemKind::VARIABLE
+                    }
+                };
+                completion_item(&definition.name, kind)
+            }))
+            .collect();
+        items.sort_by(|left, right| left.label.cmp(&right.label));
+        items.dedup_by(|left, right| left.label == right.label);
+        serde_json::to_value(CompletionResponse::Array(items)).unwrap_or_default()
+    }
+}
+
+fn params<P: DeserializeOwned>(request: Request) -> Result<P, String> {
+    let Request { method, params, .. } = request;
+    serde_json::from_value(params)
+        .map_err(|error| format!("invalid parameters for '{method}': {error}"))
+}
+
+// A notification can't be answered, so one with malformed parameters is
+// reported on stderr and ignored.
+fn notification_params<P: DeserializeOwned>(notification: Notification) -> Option<P> {
+    let Notification { method, params } = notification;
+    serde_json::from_value(params)
+        .map_err(|error| eprintln!("Error: ignoring '{method}', invalid parameters: {error}"))
+        .ok()
+}
+
+fn parse(source: &str) -> Option<Vec<Stmt>> {
+    Scanner::scan(source)
+        .and_then(|tokens| Parser::parse(&tokens))
+        .ok()
+}
+
+fn markdown(value: String) -> HoverContents {
+    HoverContents::Markup(MarkupContent {
+        kind: MarkupKind::Markdown,
+        value,
+    })
+}
+
+fn completion_item(label: &str, kind: CompletionItemKind) -> CompletionItem {
+    CompletionItem {
+        label: label.to_string(),
+        kind: Some(kind),
+        ..CompletionItem::default()
+    }
+}
+
+fn diagnostics(uri: &Url, source: &str) -> Vec<Diagnostic> {
+    let statements = Scanner::scan(source).and_then(|tokens| Parser::parse(&tokens));
+    let errors = match statements {
+        Ok(statements) => {
+            let global = Environment::new();
+            natives::define_globals(&mut global.borrow_mut());
+            let resolved = Resolver::resolve(&statements, &global.borrow());
+            resolved.err().unwrap_or_default()
+        }
+        Err(errors) => {
+            return errors
+                .iter()
+                .map(|error| error_diagnostic(source, error))
+                .collect()
+        }
+    };
+
+    let disabled = uri
+        .to_file_path()
+        .ok()
+        .and_then(|path| linter::disabled_lints(&path).ok())
+        .unwrap_or_default();
+    let warnings = linter::lint(source, &disabled).unwrap_or_default();
+    errors
+        .iter()
+        .map(|error| error_diagnostic(source, error))
+        .chain(warnings.into_iter().map(|warning| Diagnostic {
+            range: line_range(source, warning.line),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(NumberOrString::String(warning.lint.code().to_string())),
+            source: Some("rlox".to_string()),
+            message: format!("{}: {}", warning.lint.name(), warning.message),
+            ..Diagnostic::default()
+        }))
+        .collect()
+}
+
+fn error_diagnostic(source: &str, error: &LoxError) -> Diagnostic {
+    // errors without a position are shown on the first line
+    let line = match error {
+        LoxError::Static { line, .. } => *line,
+        _ => 1,
+    };
+    Diagnostic {
+        range: line_range(source, line),
+        severity: Some(DiagnosticSeverity::ERROR),
+        source: Some("rlox".to_string()),
+        message: error.to_string(),
+        ..Diagnostic::default()
+    }
+}
+
+fn line_range(source: &str, line: usize) -> Range {
+    let length = source
+        .lines()
+        .nth(line - 1)
+        .map_or(0, |text| text.chars().map(char::len_utf16).sum::<usize>());
+    Range::new(
+        Position::new(to_u32(line - 1), 0),
+        Position::new(to_u32(line - 1), to_u32(length)),
+    )
+}
+
+// LSP positions count UTF-16 code units from zero, tokens count characters from one.
+fn to_position(source: &str, line: usize, column: usize) -> Position {
+    let text = source.lines().nth(line - 1).unwrap_or_default();
+    let character = text
+        .chars()
+        .take(column - 1)
+        .map(char::len_utf16)
+        .sum::<usize>();
+    Position::new(to_u32(line - 1), to_u32(character))
+}
+
+fn from_position(source: &str, position: Position) -> (usize, usize) {
+    let line = position.line as usize;
+    let text = source.lines().nth(line).unwrap_or_default();
+    let mut units = 0;
+    let mut column = 1;
+    for c in text.chars() {
+        if units >= position.character as usize {
+            break;
+        }
+        units += c.len_utf16();
+        column += 1;
+    }
+    (line + 1, column)
+}
+
+fn token_range(source: &str, token: &Token) -> Range {
+    let length = token.lexeme.chars().count();
+    Range::new(
+        to_position(source, token.line, token.column),
+        to_position(source, token.line, token.column + length),
+    )
+}
+
+fn to_u32(value: usize) -> u32 {
+    u32::try_from(value).unwrap_or(u32::MAX)
+}
+
+fn covers(token: &Token, line: usize, column: usize) -> bool {
+    token.line == line
+        && (token.column..=token.column + token.lexeme.chars().count()).contains(&column)
+}
+
+fn document_symbols(source: &str, statements: &[Stmt]) -> Vec<DocumentSymbol> {
+    let mut symbols = Vec::new();
+    for statement in statements {
+        let (name, kind, detail, children) = match &statement.kind {
+            StmtKind::Var(name, _) => (name, SymbolKind::VARIABLE, None, None),
+            StmtKind::Const(name, _) => (name, SymbolKind::CONSTANT, None, None),
+            StmtKind::Function(func_stmt) => (
+                &func_stmt.name,
+                SymbolKind::FUNCTION,
+                Some(signature(func_stmt)),
+                Some(document_symbols(source, &func_stmt.body)),
+            ),
+            StmtKind::Block(statements) => {
+                symbols.extend(document_symbols(source, statements));
+                continue;
+            }
+            StmtKind::If(_, then_branch, else_branch) => {
+                let branches = std::iter::once(then_branch.as_ref()).chain(else_branch.as_deref());
+                for branch in branches {
+                    symbols.extend(document_symbols(source, std::slice::from_ref(branch)));
+                }
+                continue;
+            }
+            StmtKind::While(_, body) | StmtKind::For(_, _, _, body) => {
+                symbols.extend(document_symbols(source, std::slice::from_ref(body)));
+                continue;
+            }
+            StmtKind::Expr(_) | StmtKind::Print(_) | StmtKind::Ret(_) => continue,
+        };
+        let end_line = source
+            .lines()
+            .nth(statement.end_line - 1)
+            .unwrap_or_default();
+        let range = Range::new(
+            Position::new(to_u32(statement.line - 1), 0),
+            to_position(source, statement.end_line, end_line.chars().count() + 1),
+        );
+        #[allow(deprecated)]
+        symbols.push(DocumentSymbol {
+            name: name.identifier().to_string(),
+            detail,
+            kind,
+            tags: None,
+            deprecated: None,
+            range,
+            selection_range: token_range(source, name),
+            children,
+        });
+    }
+    symbols
+}
+
+fn signature(func_stmt: &FuncStmt) -> String {
+    let params = func_stmt
+        .params
+        .iter()
+        .map(Token::identifier)
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("fun {}({})", func_stmt.name.identifier(), params)
+}
+
+enum DefinitionKind {
+    Variable,
+    Constant,
+    Parameter,
+    Function {
+        signature: String,
+        arity: usize,
+        doc: Option<String>,
+    },
+}
+
+struct Definition {
+    name: String,
+    token: Token,
+    kind: DefinitionKind,
+}
+
+impl Definition {
+    fn describe(&self) -> String {
+        match &self.kind {
+            DefinitionKind::Variable => format!("```lox\nvar {}\n```", self.name),
+            DefinitionKind::Constant => format!("```lox\nconst {}\n```", self.name),
+            DefinitionKind::Parameter => format!("```lox\n{}\n```\nparameter", self.name),
+            DefinitionKind::Function {
+                signature,
+                arity,
+                doc,
+            } => {
+                let mut description = format!("```lox\n{signature}\n```\ntakes {arity} arguments");
+                if let Some(doc) = doc {
+                    description.push_str("\n\n");
+                    description.push_str(doc);
+                }
+                description
+            }
+        }
+    }
+}
+
+// Every declaration in a document and the identifiers referring to each one.
+#[derive(Default)]
+struct Index {
+    definitions: Vec<Definition>,
+    references: Vec<(Token, usize)>,
+}
+
+impl Index {
+    fn build(statements: &[Stmt]) -> Index {
+        let mut indexer = Indexer {
+            index: Index::default(),
+            scopes: vec![HashMap::new()],
+            unresolved: Vec::new(),
+        };
+        indexer.statements(statements);
+        // globals are looked up when the code runs, so they may be declared after their use
+        for token in std::mem::take(&mut indexer.unresolved) {
+            if let Some(&definition) = indexer.scopes[0].get(token.identifier()) {
+                indexer.index.references.push((token, definition));
+            }
+        }
+        indexer.index
+    }
+
+    fn definition_at(&self, line: usize, column: usize) -> Option<usize> {
+        let definition = self
+            .definitions
+            .iter()
+            .position(|definition| covers(&definition.token, line, column));
+        definition.or_else(|| {
+            self.references
+                .iter()
+                .find(|(token, _)| covers(token, line, column))
+                .map(|(_, definition)| *definition)
+        })
+    }
+
+    fn references_to(&self, definition: usize) -> impl Iterator<Item = &Token> {
+        self.references
+            .iter()
+            .filter(move |(_, target)| *target == definition)
+            .map(|(token, _)| token)
+    }
+}
+
+struct Indexer {
+    index: Index,
+    scopes: Vec<HashMap<String, usize>>,
+    unresolved: Vec<Token>,
+}
+
+impl Indexer {
+    fn define(&mut self, token: &Token, kind: DefinitionKind) {
+        let name = token.identifier().to_string();
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.clone(), self.index.definitions.len());
+        }
+        self.index.definitions.push(Definition {
+            name,
+            token: token.clone(),
+            kind,
+        });
+    }
+
+    fn reference(&mut self, token: &Token) {
+        let definition = self
+            .scopes
+            .iter()
+            .skip(1)
+            .rev()
+            .find_map(|scope| scope.get(token.identifier()));
+        match definition {
+            Some(&definition) => self.index.references.push((token.clone(), definition)),
+            None => self.unresolved.push(token.clone()),
+        }
+    }
+
+    fn scoped<F>(&mut self, body: F)
+    where
+        F: FnOnce(&mut Indexer),
+    {
+        self.scopes.push(HashMap::new());
+        body(self);
+        self.scopes.pop();
+    }
+
+    fn statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Stmt) {
+        match &statement.kind {
+            StmtKind::Block(statements) => self.scoped(|indexer| indexer.statements(statements)),
+            StmtKind::Expr(expression) | StmtKind::Print(expression) => {
+                self.expression(expression);
+            }
+            StmtKind::Function(func_stmt) => {
+                let kind = DefinitionKind::Function {
+                    signature: signature(func_stmt),
+                    arity: func_stmt.params.len(),
+                    doc: func_stmt.doc.clone(),
+                };
+                self.define(&func_stmt.name, kind);
+                self.scoped(|indexer| {
+                    for param in &func_stmt.params {
+                        indexer.define(param, DefinitionKind::Parameter);
+                    }
+                    indexer.statements(&func_stmt.body);
+                });
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.expression(condition);
+                self.statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+            }
+            StmtKind::Var(name, initializer) => {
+                if let Some(initializer) = initializer {
+                    self.expression(initializer);
+                }
+                self.define(name, DefinitionKind::Variable);
+            }
+            StmtKind::Const(name, initializer) => {
+                self.expression(initializer);
+                self.define(name, DefinitionKind::Constant);
+            }
+            StmtKind::While(condition, body) => {
+                self.expression(condition);
+                self.statement(body);
+            }
+            StmtKind::For(initializer, condition, increment, body) => self.scoped(|indexer| {
+                if let Some(initializer) = initializer {
+                    indexer.statement(initializer);
+                }
+                for expression in condition.iter().chain(increment) {
+                    indexer.expression(expression);
+                }
+                indexer.statement(body);
+            }),
+            StmtKind::Ret(value) => {
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &Expr) {
+        match expression {
+            Expr::Literal(_) => {}
+            Expr::Variable(name) => self.reference(name),
+            Expr::Assignment(name, value) => {
+                self.expression(value);
+                self.reference(name);
+            }
+            Expr::Grouping(expression) | Expr::Unary(_, expression) => {
+                self.expression(expression);
+            }
+            Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expr::Conditional(condition, then_branch, else_branch) => {
+                self.expression(condition);
+                self.expression(then_branch);
+                self.expression(else_branch);
+            }
+            Expr::Call(callee, _, arguments) => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{
+        PartialResultParams, ReferenceContext, TextDocumentIdentifier, TextDocumentItem,
+        WorkDoneProgressParams,
+    };
+    use serde_json::json;
+
+    const SOURCE: &str = "\
+var count = 1;
+fun bump(step) {
+  count = count + step;
+  return count;
+}
+print bump(2);
+";
+
+    // A server talking over an in-memory connection, with one open document.
+    struct Session {
+        server: Connection,
+        client: Connection,
+        documents: HashMap<Url, String>,
+    }
+
+    impl Session {
+        fn open(source: &str) -> Session {
+            let (server, client) = Connection::memory();
+            let mut session = Session {
+                server,
+                client,
+                documents: HashMap::new(),
+            };
+            let params = DidOpenTextDocumentParams {
+                text_document: TextDocumentItem::new(uri(), "lox".to_string(), 1, source.into()),
+            };
+            session.notify(Notification::new(
+                DidOpenTextDocument::METHOD.to_string(),
+                params,
+            ));
+            session
+        }
+
+        fn with_server(&mut self, action: impl FnOnce(&mut Server)) {
+            let mut server = Server {
+                connection: &self.server,
+                documents: std::mem::take(&mut self.documents),
+                natives: natives::arities(),
+            };
+            action(&mut server);
+            self.documents = server.documents;
+        }
+
+        fn notify(&mut self, notification: Notification) {
+            self.with_server(|server| server.notification(notification).unwrap());
+        }
+
+        fn request<R: RequestTrait>(&mut self, params: R::Params) -> Response {
+            self.send(Request::new(1.into(), R::METHOD.to_string(), params))
+        }
+
+        fn send(&mut self, request: Request) -> Response {
+            self.with_server(|server| server.request(request).unwrap());
+            match self.client.receiver.try_recv() {
+                Ok(Message::Response(response)) => response,
+                message => panic!("expected a response, got {:?}", message),
+            }
+        }
+
+        fn diagnostics(&self) -> Vec<Diagnostic> {
+            match self.client.receiver.try_recv() {
+                Ok(Message::Notification(notification)) => {
+                    let params: PublishDiagnosticsParams =
+                        serde_json::from_value(notification.params).unwrap();
+                    params.diagnostics
+                }
+                message => panic!("expected diagnostics, got {:?}", message),
+            }
+        }
+    }
+
+    // The directory doesn't exist, so no lint config applies.
+    fn uri() -> Url {
+        Url::parse("file:///nonexistent/test.lox").unwrap()
+    }
+
+    fn position(line: u32, character: u32) -> TextDocumentPositionParams {
+        TextDocumentPositionParams::new(
+            TextDocumentIdentifier::new(uri()),
+            Position::new(line, character),
+        )
+    }
+
+    fn range(line: u32, start: u32, end: u32) -> Range {
+        Range::new(Position::new(line, start), Position::new(line, end))
+    }
+
+    #[test]
+    fn finds_the_definition() {
+        let mut session = Session::open(SOURCE);
+        session.diagnostics();
+        let response = session.request::<GotoDefinition>(GotoDefinitionParams {
+            text_document_position_params: position(2, 11),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        });
+        assert_eq!(
+            response.result,
+            Some(json!({ "uri": uri(), "range": range(0, 4, 9) }))
+        );
+    }
+
+    #[test]
+    fn keeps_serving_after_malformed_params() {
+        let mut session = Session::open(SOURCE);
+        session.diagnostics();
+        let malformed = json!({ "textDocument": 3 });
+        session.notify(Notification::new(
+            DidChangeTextDocument::METHOD.to_string(),
+            malformed.clone(),
+        ));
+        let response = session.send(Request::new(
+            1.into(),
+            GotoDefinition::METHOD.to_string(),
+            malformed,
+        ));
+        let error = response.error.expect("an error response");
+        assert_eq!(error.code, ErrorCode::InvalidParams as i32);
+
+        let response = session.request::<GotoDefinition>(GotoDefinitionParams {
+            text_document_position_params: position(2, 11),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        });
+        assert_eq!(
+            response.result,
+            Some(json!({ "uri": uri(), "range": range(0, 4, 9) }))
+        );
+    }
+
+    #[test]
+    fn finds_the_references() {
+        let mut session = Session::open(SOURCE);
+        session.diagnostics();
+        let response = session.request::<References>(ReferenceParams {
+            text_document_position: position(3, 10),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+            context: ReferenceContext {
+                include_declaration: true,
+            },
+        });
+        let locations: Vec<Location> = serde_json::from_value(response.result.unwrap()).unwrap();
+        let mut ranges: Vec<Range> = locations
+            .into_iter()
+            .map(|location| location.range)
+            .collect();
+        ranges.sort_by_key(|range| (range.start.line, range.start.character));
+        assert_eq!(
+            ranges,
+            [
+                range(0, 4, 9),
+                range(2, 2, 7),
+                range(2, 10, 15),
+                range(3, 9, 14)
+            ]
+        );
+    }
+
+    #[test]
+    fn renames_the_definition_and_its_references() {
+        let mut session = Session::open(SOURCE);
+        session.diagnostics();
+        let response = session.request::<Rename>(RenameParams {
+            text_document_position: position(1, 10),
+            new_name: "amount".to_string(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        });
+        let edit: WorkspaceEdit = serde_json::from_value(response.result.unwrap()).unwrap();
+        let edits = &edit.changes.unwrap()[&uri()];
+        let ranges: Vec<Range> = edits.iter().map(|edit| edit.range).collect();
+        assert_eq!(ranges, [range(1, 9, 13), range(2, 18, 22)]);
+        assert!(edits.iter().all(|edit| edit.new_text == "amount"));
+    }
+
+    #[test]
+    fn refuses_to_rename_to_a_keyword() {
+        let mut session = Session::open(SOURCE);
+        session.diagnostics();
+        let response = session.request::<Rename>(RenameParams {
+            text_document_position: position(1, 10),
+            new_name: "while".to_string(),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        });
+        assert_eq!(
+            response.error.unwrap().message,
+            "'while' is not a valid identifier"
+        );
+    }
+
+    #[test]
+    fn publishes_errors_and_warnings() {
+        let session = Session::open(
+            "fun f(a) {\n  var b = 1;\n  return a;\n}\nconst limit = f(1);\nvar limit = 2;\n",
+        );
+        let diagnostics: Vec<_> = session
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.severity))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            [
+                (5, Some(DiagnosticSeverity::ERROR)),
+                (1, Some(DiagnosticSeverity::WARNING)),
+                (5, Some(DiagnosticSeverity::WARNING))
+            ]
+        );
+    }
+
+    #[test]
+    fn publishes_syntax_errors_on_their_line() {
+        let session = Session::open("var a = 1;\n\nprint a +;\nvar s = \"open;\n");
+        let lines: Vec<_> = session
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| diagnostic.range.start.line)
+            .collect();
+        assert_eq!(lines, [3]);
+
+        let session = Session::open("var a = 1;\n\nprint a +;\n");
+        let lines: Vec<_> = session
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| diagnostic.range.start.line)
+            .collect();
+        assert_eq!(lines, [2]);
+    }
+
+    #[test]
+    fn publishes_nothing_for_a_clean_document() {
+        assert!(Session::open(SOURCE).diagnostics().is_empty());
+    }
+}
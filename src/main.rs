@@ -1,76 +1,453 @@
 #![warn(clippy::all)]
 #![warn(clippy::pedantic)]
 
-mod environment;
-mod expression;
-mod interpreter;
-mod parser;
-mod scanner;
-mod statement;
-mod token;
-mod value;
-
-use interpreter::Interpreter;
-use parser::Parser;
-use scanner::Scanner;
-use std::io::Write;
+mod repl;
+
+use rlox::expression::Expr;
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::resolver::Resolver;
+use rlox::scanner::Scanner;
+use rlox::statement::{Stmt, StmtKind};
+use rlox::token::Token;
+use rlox::value::LoxError;
+use rlox::{ast_printer, coverage, dap, debugger, formatter, linter, lsp, profiler, test_runner};
+use std::fmt::Write;
+use std::path::Path;
+use std::time::Instant;
 
 struct Lox {
     interpreter: Interpreter,
+    // the code a program passed to `exit`, which ends the session
+    exit_code: Option<i32>,
 }
 
 impl Lox {
-    fn run_file(&mut self, path: &str) {
-        println!("{}", path);
-        let contents =
-            std::fs::read_to_string(path).expect("Something went wrong when reading file");
-        self.run(&contents);
-    }
-
-    fn run_prompt(&mut self) {
-        let mut input = String::new();
-
-        loop {
-            print!("> ");
-            std::io::stdout().flush().unwrap();
-            std::io::stdin()
-                .read_line(&mut input)
-                .expect("error: unable to read user input");
-            self.run(&input);
-            input.clear();
-        }
-    }
-
-    fn run(&mut self, source: &str) {
-        let result = Scanner::scan(source)
-            .and_then(|tokens| Parser::parse(&tokens))
-            .and_then(|statements| {
-                self.interpreter
-                    .interpret(&statements)
-                    .map_err(|error| vec![error])
-            });
-        match result {
-            Ok(_) => {}
-            Err(strings) => {
-                for string in strings {
-                    println!("Error: {}", string)
+    // Returns the process exit code.
+    fn run_file(&mut self, path: &str) -> i32 {
+        let contents = Lox::read_source(path);
+        self.run(&contents)
+    }
+
+    // Returns the process exit code.
+    fn debug_file(&mut self, path: &str) -> i32 {
+        let contents = Lox::read_source(path);
+        println!("Debugging {path}, type 'help' for the commands.");
+        self.interpreter
+            .set_hook(Box::new(debugger::Debugger::new(&contents)));
+        self.run(&contents)
+    }
+
+    // Runs the file, then prints where the time went and writes the collapsed
+    // call stacks next to it. Returns the process exit code.
+    fn profile_file(&mut self, path: &str) -> i32 {
+        let contents = Lox::read_source(path);
+        let profile = profiler::Profile::new();
+        self.interpreter
+            .set_hook(Box::new(profiler::Profiler::new(&profile)));
+        let status = self.run(&contents);
+        let mut profile = profile.borrow_mut();
+        profile.finish();
+
+        println!();
+        print!("{}", profile.summary());
+        let stacks_path = Path::new(path).with_extension("folded");
+        match std::fs::write(&stacks_path, profile.collapsed_stacks()) {
+            Ok(()) => {
+                println!();
+                println!("Collapsed stacks written to {}", stacks_path.display());
+                status
+            }
+            Err(error) => {
+                println!(
+                    "Error: unable to write '{}': {}",
+                    stacks_path.display(),
+                    error
+                );
+                73
+            }
+        }
+    }
+
+    // Runs the file, then prints which lines and branches ran and writes the
+    // lcov report next to it. Returns the process exit code.
+    fn coverage_file(&mut self, path: &str) -> i32 {
+        let contents = Lox::read_source(path);
+        let statements = match self.compile(&contents) {
+            Ok(statements) => statements,
+            Err(errors) => {
+                Lox::report(&errors);
+                return 65;
+            }
+        };
+        let coverage = coverage::Coverage::new(&statements);
+        self.interpreter
+            .set_hook(Box::new(coverage::CoverageHook::new(&coverage)));
+        let status = match self.interpreter.interpret(&statements) {
+            Ok(()) => 0,
+            Err(error) => self.fail(&error),
+        };
+        let coverage = coverage.borrow();
+
+        println!();
+        print!("{}", coverage.summary());
+        let source_path = std::fs::canonicalize(path).map_or(path.to_string(), |source_path| {
+            source_path.display().to_string()
+        });
+        let lcov_path = Path::new(path).with_extension("lcov");
+        match std::fs::write(&lcov_path, coverage.lcov(&source_path)) {
+            Ok(()) => {
+                println!();
+                println!("Coverage report written to {}", lcov_path.display());
+                status
+            }
+            Err(error) => {
+                println!(
+                    "Error: unable to write '{}': {}",
+                    lcov_path.display(),
+                    error
+                );
+                73
+            }
+        }
+    }
+
+    // Like `run`, but a bare expression is evaluated and its value printed.
+    fn run_interactive(&mut self, source: &str) {
+        let expression = Scanner::scan(source)
+            .ok()
+            .and_then(|tokens| Parser::parse_expression(&tokens).ok());
+        match expression {
+            Some(expression) => self.evaluate(&expression),
+            None => {
+                self.run(source);
+            }
+        }
+    }
+
+    fn evaluate(&mut self, expression: &Expr) {
+        let statements = [Stmt {
+            line: expression.line(),
+            end_line: expression.line(),
+            column: expression.first_token().column,
+            kind: StmtKind::Expr(expression.clone()),
+        }];
+        if let Err(errors) = Resolver::resolve(&statements, &self.interpreter.global.borrow()) {
+            return Lox::report(&errors);
+        }
+        match self.interpreter.interpret_expression(expression) {
+            Ok(value) => println!("{value}"),
+            Err(error) => {
+                self.fail(&error);
+            }
+        }
+    }
+
+    // Scans, parses and resolves a program ready to be interpreted.
+    fn compile(&self, source: &str) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let statements = Scanner::scan(source).and_then(|tokens| Parser::parse(&tokens))?;
+        Resolver::resolve(&statements, &self.interpreter.global.borrow())?;
+        Ok(statements)
+    }
+
+    // Returns the process exit code: 65 when the program doesn't compile, 70
+    // when it fails at runtime, or the code it passed to `exit`.
+    fn run(&mut self, source: &str) -> i32 {
+        let statements = match self.compile(source) {
+            Ok(statements) => statements,
+            Err(errors) => {
+                Lox::report(&errors);
+                return 65;
+            }
+        };
+        match self.interpreter.interpret(&statements) {
+            Ok(()) => 0,
+            Err(error) => self.fail(&error),
+        }
+    }
+
+    // Reports the error a program ended with, returning the exit code.
+    fn fail(&mut self, error: &LoxError) -> i32 {
+        if let LoxError::Exit(code) = error {
+            self.exit_code = Some(*code);
+            return *code;
+        }
+        Lox::report(std::slice::from_ref(error));
+        70
+    }
+
+    fn dump_tokens(path: &str) {
+        let contents = Lox::read_source(path);
+        match Scanner::scan(&contents) {
+            Ok(tokens) => print!("{}", Lox::format_tokens(&tokens)),
+            Err(errors) => Lox::report(&errors),
+        }
+    }
+
+    fn dump_ast(path: &str, printer: fn(&[Stmt]) -> String) {
+        let contents = Lox::read_source(path);
+        match Scanner::scan(&contents).and_then(|tokens| Parser::parse(&tokens)) {
+            Ok(statements) => print!("{}", printer(&statements)),
+            Err(errors) => Lox::report(&errors),
+        }
+    }
+
+    // Rewrites each file in the canonical layout, or with `--check` only lists
+    // the files that need it. Returns the process exit code.
+    fn format_files(args: &[String]) -> i32 {
+        let (check, paths) = match args {
+            [flag, paths @ ..] if flag == "--check" => (true, paths),
+            paths => (false, paths),
+        };
+        if paths.is_empty() {
+            println!("usage: rlox fmt [--check] <file>...");
+            return 64;
+        }
+
+        let mut status = 0;
+        for path in paths {
+            let contents = match std::fs::read_to_string(path) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    println!("Error: unable to read '{path}': {error}");
+                    status = 66;
+                    continue;
+                }
+            };
+            let formatted = match formatter::format(&contents) {
+                Ok(formatted) => formatted,
+                Err(errors) => {
+                    println!("{path}");
+                    Lox::report(&errors);
+                    status = 65;
+                    continue;
                 }
+            };
+            if formatted == contents {
+                continue;
             }
+            if check {
+                println!("{path} is not formatted");
+                status = status.max(1);
+            } else if let Err(error) = std::fs::write(path, formatted) {
+                println!("Error: unable to write '{path}': {error}");
+                status = 73;
+            }
+        }
+        status
+    }
+
+    // Prints the lint warnings for each file. Returns the process exit code.
+    fn lint_files(paths: &[String]) -> i32 {
+        if paths.is_empty() {
+            println!("usage: rlox lint <file>...");
+            return 64;
+        }
+
+        let mut status = 0;
+        for path in paths {
+            let contents = match std::fs::read_to_string(path) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    println!("Error: unable to read '{path}': {error}");
+                    status = 66;
+                    continue;
+                }
+            };
+            let warnings = linter::disabled_lints(Path::new(path))
+                .map_err(|error| vec![error])
+                .and_then(|disabled| linter::lint(&contents, &disabled));
+            match warnings {
+                Ok(warnings) => {
+                    for warning in &warnings {
+                        println!(
+                            "{}:{}: warning[{}] {}: {}",
+                            path,
+                            warning.line,
+                            warning.lint.code(),
+                            warning.lint.name(),
+                            warning.message
+                        );
+                    }
+                    if !warnings.is_empty() {
+                        status = status.max(1);
+                    }
+                }
+                Err(errors) => {
+                    println!("{path}");
+                    Lox::report(&errors);
+                    status = 65;
+                }
+            }
+        }
+        status
+    }
+
+    // Runs the tests of every file and reports each one. Returns the process exit code.
+    fn test_files(paths: &[String]) -> i32 {
+        if paths.is_empty() {
+            println!("usage: rlox test <file or directory>...");
+            return 64;
+        }
+        let files = match test_runner::discover(paths) {
+            Ok(files) => files,
+            Err(error) => {
+                Lox::report(&[error]);
+                return 66;
+            }
+        };
+
+        let start = Instant::now();
+        let (mut passed, mut failed) = (0, 0);
+        let mut status = 0;
+        for file in files {
+            println!("{}", file.display());
+            let contents = match std::fs::read_to_string(&file) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    println!("Error: unable to read '{}': {}", file.display(), error);
+                    status = 66;
+                    continue;
+                }
+            };
+            let outcomes = match test_runner::run(&contents) {
+                Ok(outcomes) => outcomes,
+                Err(errors) => {
+                    Lox::report(&errors);
+                    status = 65;
+                    continue;
+                }
+            };
+            for outcome in outcomes {
+                let time = outcome.time.as_secs_f64() * 1000.0;
+                match outcome.failure {
+                    None => {
+                        println!("  ok      {} ({:.3} ms)", outcome.name, time);
+                        passed += 1;
+                    }
+                    Some(failure) => {
+                        println!("  FAILED  {} ({:.3} ms)", outcome.name, time);
+                        for line in failure.lines() {
+                            println!("    {line}");
+                        }
+                        failed += 1;
+                    }
+                }
+            }
+        }
+
+        println!();
+        println!(
+            "{} passed, {} failed in {:.3} ms",
+            passed,
+            failed,
+            start.elapsed().as_secs_f64() * 1000.0
+        );
+        if failed > 0 {
+            status = status.max(1);
+        }
+        status
+    }
+
+    fn format_tokens(tokens: &[Token]) -> String {
+        let mut output = String::new();
+        for token in tokens {
+            let position = format!("{}:{}", token.line, token.column);
+            let _ = writeln!(output, "{:<8}{:?}", position, token.token_type);
+        }
+        output
+    }
+
+    // Reads a script, exiting when it can't be read.
+    fn read_source(path: &str) -> String {
+        std::fs::read_to_string(path).unwrap_or_else(|error| {
+            println!("Error: unable to read '{path}': {error}");
+            std::process::exit(66);
+        })
+    }
+
+    fn report(errors: &[LoxError]) {
+        for error in errors {
+            println!("Error: {error}");
         }
     }
 }
 
+// Every Lox call and nested expression recurses in the interpreter, this
+// leaves room for the deepest programs the parser and interpreter accept.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
 fn main() {
+    let result = std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .map(std::thread::JoinHandle::join);
+    // a panic has already been reported by the thread
+    if !matches!(result, Ok(Ok(()))) {
+        std::process::exit(70);
+    }
+}
+
+fn run() {
     let mut lox = Lox {
         interpreter: Interpreter::new(),
+        exit_code: None,
     };
 
-    let args: Vec<String> = std::env::args().collect();
-    match args.len() {
-        1 => lox.run_prompt(),
-        2 => lox.run_file(&args[1]),
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.as_slice() {
+        [] => repl::run(&mut lox),
+        [flag, path] if flag == "--dump-tokens" => Lox::dump_tokens(path),
+        [flag, path] if flag == "--dump-ast" || flag == "--dump-ast=tree" => {
+            Lox::dump_ast(path, ast_printer::tree);
+        }
+        [flag, path] if flag == "--dump-ast=sexpr" => Lox::dump_ast(path, ast_printer::sexpr),
+        [flag, path, args @ ..] if flag == "--profile" => {
+            lox.interpreter.set_args(args.to_vec());
+            std::process::exit(lox.profile_file(path));
+        }
+        [flag, path, args @ ..] if flag == "--coverage" => {
+            lox.interpreter.set_args(args.to_vec());
+            std::process::exit(lox.coverage_file(path));
+        }
+        [command, args @ ..] if command == "fmt" => std::process::exit(Lox::format_files(args)),
+        [command, paths @ ..] if command == "lint" => std::process::exit(Lox::lint_files(paths)),
+        [command, paths @ ..] if command == "test" => std::process::exit(Lox::test_files(paths)),
+        [command, path, args @ ..] if command == "debug" => {
+            lox.interpreter.set_args(args.to_vec());
+            std::process::exit(lox.debug_file(path));
+        }
+        [command] if command == "lsp" => {
+            // stdout carries the protocol, so failures go to stderr
+            if let Err(error) = lsp::run() {
+                eprintln!("Error: language server failed: {error}");
+                std::process::exit(70);
+            }
+        }
+        [command] if command == "dap" => {
+            if let Err(error) = dap::run() {
+                eprintln!("Error: debug adapter failed: {error}");
+                std::process::exit(70);
+            }
+        }
+        // `--` runs a script named like a subcommand, such as `rlox -- test`
+        [separator, path, args @ ..] if separator == "--" => {
+            lox.interpreter.set_args(args.to_vec());
+            std::process::exit(lox.run_file(path));
+        }
+        [path, args @ ..] if !path.starts_with("--") => {
+            lox.interpreter.set_args(args.to_vec());
+            std::process::exit(lox.run_file(path));
+        }
         _ => {
-            println!("usage: rlox [script]");
+            println!("usage: rlox [--dump-tokens | --dump-ast[=tree|sexpr] | --profile | --coverage] [script [args...]]");
+            println!("       rlox -- <script> [args...]    (for a script named fmt, lint, test, debug, lsp or dap)");
+            println!("       rlox fmt [--check] <file>...");
+            println!("       rlox lint <file>...");
+            println!("       rlox test <file or directory>...");
+            println!("       rlox lsp");
+            println!("       rlox debug <script> [args...]");
+            println!("       rlox dap");
             std::process::exit(64);
         }
     }
@@ -0,0 +1,246 @@
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::scanner::Scanner;
+use crate::token::{Token, TokenType};
+use crate::value::{Callable, LoxError, LoxValue, INTEGER_RANGE};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::time::UNIX_EPOCH;
+
+mod fs;
+mod io;
+mod math;
+mod string;
+mod system;
+
+type NativeFn = fn(&mut Interpreter, &[LoxValue]) -> Result<LoxValue, LoxError>;
+
+pub fn define_globals(global: &mut Environment) {
+    define(global, "clock", 0, clock);
+    define(global, "int", 1, int);
+    define(global, "float", 1, float);
+    define(global, "str", 1, str);
+    define(global, "num", 1, num);
+    define(global, "bool", 1, bool);
+    define(global, "type", 1, type_of);
+    define(global, "arity", 1, arity);
+    define(global, "assert", 2, assert);
+    define(global, "assert_eq", 2, assert_eq);
+    math::define_globals(global);
+    string::define_globals(global);
+    fs::define_globals(global);
+    system::define_globals(global);
+    io::define_globals(global);
+}
+
+// The arity of each native function, by name.
+pub fn arities() -> HashMap<String, usize> {
+    let global = Environment::new();
+    define_globals(&mut global.borrow_mut());
+    let global = global.borrow();
+    global
+        .bindings()
+        .filter_map(|(name, value, _)| match value {
+            LoxValue::Callable(callable) => Some((name.clone(), callable.arity())),
+            _ => None,
+        })
+        .collect()
+}
+
+fn define(global: &mut Environment, name: &str, arity: usize, func: NativeFn) {
+    let callable = LoxValue::Callable(Callable::Native { arity, func });
+    constant(global, name, &callable);
+}
+
+fn constant(global: &mut Environment, name: &str, value: &LoxValue) {
+    let defined = global.define_constant(name, value);
+    debug_assert!(defined.is_ok(), "the global '{}' is defined twice", name);
+}
+
+// The argument as a string, or the error naming the native that wanted it.
+fn string<'a>(
+    interpreter: &Interpreter,
+    name: &str,
+    argument: &'a LoxValue,
+) -> Result<&'a str, LoxError> {
+    match argument {
+        LoxValue::String(string) => Ok(string),
+        _ => Err(error_in_line(
+            interpreter,
+            &format!("{name}() expects a string."),
+        )),
+    }
+}
+
+// The argument as a position or count.
+fn index(interpreter: &Interpreter, name: &str, argument: &LoxValue) -> Result<usize, LoxError> {
+    match argument {
+        LoxValue::Integer(integer) => usize::try_from(*integer).map_err(|_| {
+            error_in_line(
+                interpreter,
+                &format!("{name}() expects a non-negative integer."),
+            )
+        }),
+        _ => Err(error_in_line(
+            interpreter,
+            &format!("{name}() expects an integer."),
+        )),
+    }
+}
+
+// Lox has no lists, so a native giving several values takes an index `n` as
+// its last argument and returns the n-th value, or nil once the values run
+// out. Scripts count `n` up from 0 until they get nil.
+fn nth(
+    interpreter: &Interpreter,
+    name: &str,
+    values: impl IntoIterator<Item = String>,
+    n: &LoxValue,
+) -> Result<LoxValue, LoxError> {
+    let n = index(interpreter, name, n)?;
+    Ok(values
+        .into_iter()
+        .nth(n)
+        .map_or(LoxValue::Nil, LoxValue::String))
+}
+
+fn clock(interpreter: &mut Interpreter, _: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let now = std::time::SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|error| error_in_line(interpreter, &format!("clock() failed: {error}")))?;
+    interpreter.print(&format!("{now:#?}"))?;
+    Ok(LoxValue::Nil)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn int(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    match &arguments[0] {
+        LoxValue::Integer(integer) => Ok(LoxValue::Integer(*integer)),
+        LoxValue::Number(number) if INTEGER_RANGE.contains(&number.trunc()) => {
+            Ok(LoxValue::Integer(number.trunc() as i64))
+        }
+        LoxValue::Number(number) => Err(error_in_line(
+            interpreter,
+            &format!("int() cannot convert {number:?} to an integer."),
+        )),
+        _ => Err(error_in_line(interpreter, "int() expects a number.")),
+    }
+}
+
+fn float(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    arguments[0]
+        .as_float()
+        .map(LoxValue::Number)
+        .ok_or_else(|| error_in_line(interpreter, "float() expects a number."))
+}
+
+// natives share one signature, even those that can't fail
+#[allow(clippy::unnecessary_wraps)]
+fn str(_: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    Ok(LoxValue::String(arguments[0].to_string()))
+}
+
+// A string holding a number literal, as written in Lox source, gives the
+// number; any other string gives nil.
+fn num(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    match &arguments[0] {
+        LoxValue::String(text) => Ok(parse_number(text.trim()).unwrap_or(LoxValue::Nil)),
+        number @ (LoxValue::Integer(_) | LoxValue::Number(_)) => Ok(number.clone()),
+        _ => Err(error_in_line(
+            interpreter,
+            "num() expects a string or a number.",
+        )),
+    }
+}
+
+fn parse_number(text: &str) -> Option<LoxValue> {
+    let (negative, literal) = match text.strip_prefix('-') {
+        Some(literal) => (true, literal),
+        None => (false, text),
+    };
+    let tokens = Scanner::scan(literal).ok()?;
+    let value = match tokens.as_slice() {
+        [Token {
+            token_type: TokenType::Integer(integer),
+            ..
+        }, _] => LoxValue::Integer(*integer),
+        [Token {
+            token_type: TokenType::Number(number),
+            ..
+        }, _] => LoxValue::Number(*number),
+        _ => return None,
+    };
+    Some(match (negative, value) {
+        (false, value) => value,
+        (true, LoxValue::Integer(integer)) => LoxValue::Integer(integer.checked_neg()?),
+        (true, LoxValue::Number(number)) => LoxValue::Number(-number),
+        (true, _) => return None,
+    })
+}
+
+#[allow(clippy::unnecessary_wraps)]
+fn bool(_: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    Ok(LoxValue::Bool(arguments[0].is_truthy()))
+}
+
+// The names match the conversion natives, so `int(x)` makes `type(x)` "int".
+#[allow(clippy::unnecessary_wraps)]
+fn type_of(_: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let name = match &arguments[0] {
+        LoxValue::Integer(_) => "int",
+        LoxValue::Number(_) => "float",
+        LoxValue::String(_) => "string",
+        LoxValue::Bool(_) => "bool",
+        LoxValue::Nil => "nil",
+        LoxValue::Callable(_) => "function",
+        LoxValue::File(_) => "file",
+    };
+    Ok(LoxValue::String(name.to_string()))
+}
+
+fn arity(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    match &arguments[0] {
+        LoxValue::Callable(callable) => Ok(LoxValue::Integer(
+            i64::try_from(callable.arity()).unwrap_or(i64::MAX),
+        )),
+        _ => Err(error_in_line(interpreter, "arity() expects a function.")),
+    }
+}
+
+fn assert(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    if arguments[0].is_truthy() {
+        Ok(LoxValue::Nil)
+    } else {
+        Err(error_in_line(
+            interpreter,
+            &format!("assertion failed: {}", arguments[1]),
+        ))
+    }
+}
+
+fn assert_eq(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let (left, right) = (&arguments[0], &arguments[1]);
+    if left == right {
+        return Ok(LoxValue::Nil);
+    }
+    let (left, right) = (left.to_string(), right.to_string());
+    let mut message = format!("values are not equal\n  left:  {left}\n  right: {right}");
+    if left == right {
+        message.push_str("\n  the values print the same but have different types");
+    }
+    Err(error_in_line(interpreter, &message))
+}
+
+// An error of a native, naming the line of the statement calling it.
+fn error_in_line(interpreter: &Interpreter, message: &str) -> LoxError {
+    LoxError::Standard(format!(
+        "Error in line: {}, {}",
+        current_line(interpreter),
+        message
+    ))
+}
+
+// Line of the statement calling the native.
+fn current_line(interpreter: &Interpreter) -> usize {
+    interpreter.frames().last().map_or(0, |frame| frame.line)
+}
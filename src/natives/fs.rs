@@ -0,0 +1,157 @@
+use super::{define, error_in_line, nth, string};
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::value::{FileHandle, LoxError, LoxValue};
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+pub fn define_globals(global: &mut Environment) {
+    define(global, "read_file", 1, read_file);
+    define(global, "write_file", 2, write_file);
+    define(global, "append_file", 2, append_file);
+    define(global, "exists", 1, exists);
+    define(global, "list_dir", 2, list_dir);
+    define(global, "mkdir", 1, mkdir);
+    define(global, "remove", 1, remove);
+    define(global, "open", 1, open);
+    define(global, "read_line", 1, read_line);
+    define(global, "close", 1, close);
+}
+
+// The OS error of a native, with the path it was working on.
+fn failed(interpreter: &Interpreter, name: &str, path: &str, error: &std::io::Error) -> LoxError {
+    error_in_line(
+        interpreter,
+        &format!("{name}() failed on '{path}': {error}"),
+    )
+}
+
+fn read_file(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let path = string(interpreter, "read_file", &arguments[0])?;
+    std::fs::read_to_string(path)
+        .map(LoxValue::String)
+        .map_err(|error| failed(interpreter, "read_file", path, &error))
+}
+
+fn write_file(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let path = string(interpreter, "write_file", &arguments[0])?;
+    let contents = string(interpreter, "write_file", &arguments[1])?;
+    std::fs::write(path, contents)
+        .map(|()| LoxValue::Nil)
+        .map_err(|error| failed(interpreter, "write_file", path, &error))
+}
+
+fn append_file(
+    interpreter: &mut Interpreter,
+    arguments: &[LoxValue],
+) -> Result<LoxValue, LoxError> {
+    let path = string(interpreter, "append_file", &arguments[0])?;
+    let contents = string(interpreter, "append_file", &arguments[1])?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map(|()| LoxValue::Nil)
+        .map_err(|error| failed(interpreter, "append_file", path, &error))
+}
+
+fn exists(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let path = string(interpreter, "exists", &arguments[0])?;
+    Ok(LoxValue::Bool(Path::new(path).exists()))
+}
+
+// `list_dir(path, n)` gives the n-th entry name in sorted order.
+fn list_dir(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let path = string(interpreter, "list_dir", &arguments[0])?;
+    let mut names = std::fs::read_dir(path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|error| failed(interpreter, "list_dir", path, &error))?;
+    names.sort();
+    nth(interpreter, "list_dir", names, &arguments[1])
+}
+
+// Creates the missing parent directories too.
+fn mkdir(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let path = string(interpreter, "mkdir", &arguments[0])?;
+    std::fs::create_dir_all(path)
+        .map(|()| LoxValue::Nil)
+        .map_err(|error| failed(interpreter, "mkdir", path, &error))
+}
+
+// Directories must be empty, nothing is removed recursively.
+fn remove(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let path = string(interpreter, "remove", &arguments[0])?;
+    let result = if Path::new(path).is_dir() {
+        std::fs::remove_dir(path)
+    } else {
+        std::fs::remove_file(path)
+    };
+    result
+        .map(|()| LoxValue::Nil)
+        .map_err(|error| failed(interpreter, "remove", path, &error))
+}
+
+fn open(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let path = string(interpreter, "open", &arguments[0])?;
+    let file = File::open(path).map_err(|error| failed(interpreter, "open", path, &error))?;
+    Ok(LoxValue::File(Rc::new(RefCell::new(FileHandle {
+        path: path.to_string(),
+        reader: Some(BufReader::new(file)),
+    }))))
+}
+
+fn file<'a>(
+    interpreter: &Interpreter,
+    name: &str,
+    argument: &'a LoxValue,
+) -> Result<&'a Rc<RefCell<FileHandle>>, LoxError> {
+    match argument {
+        LoxValue::File(file) => Ok(file),
+        _ => Err(error_in_line(
+            interpreter,
+            &format!("{name}() expects a file."),
+        )),
+    }
+}
+
+// The next line without its line ending, or nil at the end of the file.
+fn read_line(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let mut file = file(interpreter, "read_line", &arguments[0])?.borrow_mut();
+    let FileHandle { path, reader } = &mut *file;
+    let reader = reader.as_mut().ok_or_else(|| {
+        error_in_line(
+            interpreter,
+            &format!("read_line() file '{path}' is closed."),
+        )
+    })?;
+    let mut line = String::new();
+    let read = reader
+        .read_line(&mut line)
+        .map_err(|error| failed(interpreter, "read_line", path, &error))?;
+    if read == 0 {
+        return Ok(LoxValue::Nil);
+    }
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    Ok(LoxValue::String(line))
+}
+
+// Closing twice is harmless.
+fn close(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    file(interpreter, "close", &arguments[0])?
+        .borrow_mut()
+        .reader = None;
+    Ok(LoxValue::Nil)
+}
@@ -0,0 +1,22 @@
+use super::{define, string};
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::value::{LoxError, LoxValue};
+
+pub fn define_globals(global: &mut Environment) {
+    define(global, "input", 1, input);
+    define(global, "read_all", 0, read_all);
+}
+
+// Shows the prompt, then gives the line typed or nil at the end of the input.
+fn input(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let prompt = string(interpreter, "input", &arguments[0])?;
+    interpreter.prompt(prompt)?;
+    Ok(interpreter
+        .read_line()?
+        .map_or(LoxValue::Nil, LoxValue::String))
+}
+
+fn read_all(interpreter: &mut Interpreter, _: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    interpreter.read_all().map(LoxValue::String)
+}
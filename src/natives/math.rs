@@ -0,0 +1,201 @@
+use super::{constant, define, error_in_line};
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::value::{LoxError, LoxValue};
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+pub fn define_globals(global: &mut Environment) {
+    constant(global, "PI", &LoxValue::Number(std::f64::consts::PI));
+    constant(global, "E", &LoxValue::Number(std::f64::consts::E));
+
+    define(global, "abs", 1, abs);
+    define(global, "floor", 1, floor);
+    define(global, "ceil", 1, ceil);
+    define(global, "round", 1, round);
+    define(global, "min", 2, min);
+    define(global, "max", 2, max);
+    define(global, "pow", 2, pow);
+    define(global, "sqrt", 1, sqrt);
+    define(global, "exp", 1, exp);
+    define(global, "log", 1, log);
+    define(global, "log2", 1, log2);
+    define(global, "log10", 1, log10);
+    define(global, "sin", 1, sin);
+    define(global, "cos", 1, cos);
+    define(global, "tan", 1, tan);
+    define(global, "asin", 1, asin);
+    define(global, "acos", 1, acos);
+    define(global, "atan", 1, atan);
+    define(global, "atan2", 2, atan2);
+    define(global, "isnan", 1, isnan);
+    define(global, "isinf", 1, isinf);
+}
+
+// The argument as a float, or the error naming the native that wanted it.
+fn number(interpreter: &Interpreter, name: &str, argument: &LoxValue) -> Result<f64, LoxError> {
+    argument
+        .as_float()
+        .ok_or_else(|| error_in_line(interpreter, &format!("{name}() expects a number.")))
+}
+
+fn abs(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    match &arguments[0] {
+        LoxValue::Integer(integer) => integer
+            .checked_abs()
+            .map(LoxValue::Integer)
+            .ok_or_else(|| error_in_line(interpreter, "abs() integer overflow.")),
+        argument => {
+            number(interpreter, "abs", argument).map(|number| LoxValue::Number(number.abs()))
+        }
+    }
+}
+
+// Integers are already whole, so rounding leaves them as they are.
+fn rounded(
+    interpreter: &Interpreter,
+    name: &str,
+    argument: &LoxValue,
+    round: fn(f64) -> f64,
+) -> Result<LoxValue, LoxError> {
+    match argument {
+        LoxValue::Integer(integer) => Ok(LoxValue::Integer(*integer)),
+        argument => {
+            number(interpreter, name, argument).map(|number| LoxValue::Number(round(number)))
+        }
+    }
+}
+
+fn floor(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    rounded(interpreter, "floor", &arguments[0], f64::floor)
+}
+
+fn ceil(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    rounded(interpreter, "ceil", &arguments[0], f64::ceil)
+}
+
+fn round(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    rounded(interpreter, "round", &arguments[0], f64::round)
+}
+
+fn min(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    pick(interpreter, "min", arguments, Ordering::Less)
+}
+
+fn max(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    pick(interpreter, "max", arguments, Ordering::Greater)
+}
+
+// The argument ordered first by `wanted`, returned as is so integers stay
+// integers. NaN wins so it isn't silently dropped.
+fn pick(
+    interpreter: &Interpreter,
+    name: &str,
+    arguments: &[LoxValue],
+    wanted: Ordering,
+) -> Result<LoxValue, LoxError> {
+    let (left, right) = (
+        number(interpreter, name, &arguments[0])?,
+        number(interpreter, name, &arguments[1])?,
+    );
+    let order = match (&arguments[0], &arguments[1]) {
+        // compared exactly, large integers lose precision as floats
+        (LoxValue::Integer(left), LoxValue::Integer(right)) => left.cmp(right),
+        _ => match left.partial_cmp(&right) {
+            Some(order) => order,
+            None => return Ok(LoxValue::Number(f64::NAN)),
+        },
+    };
+    Ok(if order == wanted.reverse() {
+        arguments[1].clone()
+    } else {
+        arguments[0].clone()
+    })
+}
+
+fn pow(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    if let (LoxValue::Integer(base), LoxValue::Integer(exponent)) = (&arguments[0], &arguments[1]) {
+        // a negative exponent gives a fraction, computed as floats below
+        if let Ok(exponent) = u32::try_from(*exponent) {
+            return base
+                .checked_pow(exponent)
+                .map(LoxValue::Integer)
+                .ok_or_else(|| error_in_line(interpreter, "pow() integer overflow."));
+        }
+    }
+    let (base, exponent) = (
+        number(interpreter, "pow", &arguments[0])?,
+        number(interpreter, "pow", &arguments[1])?,
+    );
+    Ok(LoxValue::Number(base.powf(exponent)))
+}
+
+fn float_function(
+    interpreter: &Interpreter,
+    name: &str,
+    argument: &LoxValue,
+    function: fn(f64) -> f64,
+) -> Result<LoxValue, LoxError> {
+    number(interpreter, name, argument).map(|number| LoxValue::Number(function(number)))
+}
+
+fn sqrt(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    float_function(interpreter, "sqrt", &arguments[0], f64::sqrt)
+}
+
+fn exp(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    float_function(interpreter, "exp", &arguments[0], f64::exp)
+}
+
+fn log(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    float_function(interpreter, "log", &arguments[0], f64::ln)
+}
+
+fn log2(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    float_function(interpreter, "log2", &arguments[0], f64::log2)
+}
+
+fn log10(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    float_function(interpreter, "log10", &arguments[0], f64::log10)
+}
+
+fn sin(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    float_function(interpreter, "sin", &arguments[0], f64::sin)
+}
+
+fn cos(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    float_function(interpreter, "cos", &arguments[0], f64::cos)
+}
+
+fn tan(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    float_function(interpreter, "tan", &arguments[0], f64::tan)
+}
+
+fn asin(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    float_function(interpreter, "asin", &arguments[0], f64::asin)
+}
+
+fn acos(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    float_function(interpreter, "acos", &arguments[0], f64::acos)
+}
+
+fn atan(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    float_function(interpreter, "atan", &arguments[0], f64::atan)
+}
+
+fn atan2(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let (y, x) = (
+        number(interpreter, "atan2", &arguments[0])?,
+        number(interpreter, "atan2", &arguments[1])?,
+    );
+    Ok(LoxValue::Number(y.atan2(x)))
+}
+
+// Integers are never NaN nor infinite.
+fn isnan(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    number(interpreter, "isnan", &arguments[0]).map(|number| LoxValue::Bool(number.is_nan()))
+}
+
+fn isinf(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    number(interpreter, "isinf", &arguments[0]).map(|number| LoxValue::Bool(number.is_infinite()))
+}
@@ -0,0 +1,187 @@
+use super::{define, error_in_line, index, nth, string};
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::value::{LoxError, LoxValue};
+use std::convert::TryFrom;
+
+// The longest string `repeat` builds, in bytes. A count mistyped as huge
+// fails with an error instead of exhausting memory.
+const MAX_REPEAT_LENGTH: usize = 1 << 27;
+
+// Positions and lengths count characters (Unicode scalar values), not bytes.
+pub fn define_globals(global: &mut Environment) {
+    define(global, "len", 1, len);
+    define(global, "substr", 3, substr);
+    define(global, "upper", 1, upper);
+    define(global, "lower", 1, lower);
+    define(global, "trim", 1, trim);
+    define(global, "split", 3, split);
+    define(global, "join", 3, join);
+    define(global, "replace", 3, replace);
+    define(global, "find", 2, find);
+    define(global, "starts_with", 2, starts_with);
+    define(global, "ends_with", 2, ends_with);
+    define(global, "char_at", 2, char_at);
+    define(global, "ord", 1, ord);
+    define(global, "chr", 1, chr);
+    define(global, "repeat", 2, repeat);
+}
+
+fn integer(count: usize) -> LoxValue {
+    LoxValue::Integer(i64::try_from(count).unwrap_or(i64::MAX))
+}
+
+fn len(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    Ok(integer(
+        string(interpreter, "len", &arguments[0])?.chars().count(),
+    ))
+}
+
+// `length` characters from `start`, fewer if the string ends first.
+fn substr(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let text = string(interpreter, "substr", &arguments[0])?;
+    let (start, length) = (
+        index(interpreter, "substr", &arguments[1])?,
+        index(interpreter, "substr", &arguments[2])?,
+    );
+    if start > text.chars().count() {
+        return Err(error_in_line(
+            interpreter,
+            &format!("substr() start {start} is past the end of the string."),
+        ));
+    }
+    Ok(LoxValue::String(
+        text.chars().skip(start).take(length).collect(),
+    ))
+}
+
+fn upper(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    Ok(LoxValue::String(
+        string(interpreter, "upper", &arguments[0])?.to_uppercase(),
+    ))
+}
+
+fn lower(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    Ok(LoxValue::String(
+        string(interpreter, "lower", &arguments[0])?.to_lowercase(),
+    ))
+}
+
+fn trim(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    Ok(LoxValue::String(
+        string(interpreter, "trim", &arguments[0])?
+            .trim()
+            .to_string(),
+    ))
+}
+
+// `split(string, separator, n)` gives the n-th piece.
+fn split(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let text = string(interpreter, "split", &arguments[0])?;
+    let separator = string(interpreter, "split", &arguments[1])?;
+    if separator.is_empty() {
+        return Err(error_in_line(interpreter, "split() separator is empty."));
+    }
+    nth(
+        interpreter,
+        "split",
+        text.split(separator).map(str::to_string),
+        &arguments[2],
+    )
+}
+
+// `join(left, separator, right)`, the counterpart of `split` that builds a
+// string a piece at a time.
+fn join(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let left = string(interpreter, "join", &arguments[0])?;
+    let separator = string(interpreter, "join", &arguments[1])?;
+    let right = string(interpreter, "join", &arguments[2])?;
+    Ok(LoxValue::String([left, separator, right].concat()))
+}
+
+fn replace(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let text = string(interpreter, "replace", &arguments[0])?;
+    let from = string(interpreter, "replace", &arguments[1])?;
+    let to = string(interpreter, "replace", &arguments[2])?;
+    if from.is_empty() {
+        return Err(error_in_line(interpreter, "replace() pattern is empty."));
+    }
+    Ok(LoxValue::String(text.replace(from, to)))
+}
+
+// The character position of the first match, or -1.
+fn find(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let text = string(interpreter, "find", &arguments[0])?;
+    let needle = string(interpreter, "find", &arguments[1])?;
+    Ok(match text.find(needle) {
+        Some(byte) => integer(text[..byte].chars().count()),
+        None => LoxValue::Integer(-1),
+    })
+}
+
+fn starts_with(
+    interpreter: &mut Interpreter,
+    arguments: &[LoxValue],
+) -> Result<LoxValue, LoxError> {
+    let text = string(interpreter, "starts_with", &arguments[0])?;
+    let prefix = string(interpreter, "starts_with", &arguments[1])?;
+    Ok(LoxValue::Bool(text.starts_with(prefix)))
+}
+
+fn ends_with(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let text = string(interpreter, "ends_with", &arguments[0])?;
+    let suffix = string(interpreter, "ends_with", &arguments[1])?;
+    Ok(LoxValue::Bool(text.ends_with(suffix)))
+}
+
+fn char_at(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let text = string(interpreter, "char_at", &arguments[0])?;
+    let position = index(interpreter, "char_at", &arguments[1])?;
+    text.chars()
+        .nth(position)
+        .map(|character| LoxValue::String(character.to_string()))
+        .ok_or_else(|| {
+            error_in_line(
+                interpreter,
+                &format!("char_at() position {position} is past the end of the string."),
+            )
+        })
+}
+
+// The code point of a one character string.
+fn ord(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let mut characters = string(interpreter, "ord", &arguments[0])?.chars();
+    match (characters.next(), characters.next()) {
+        (Some(character), None) => Ok(LoxValue::Integer(i64::from(u32::from(character)))),
+        _ => Err(error_in_line(
+            interpreter,
+            "ord() expects a single character.",
+        )),
+    }
+}
+
+fn chr(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let code_point = index(interpreter, "chr", &arguments[0])?;
+    u32::try_from(code_point)
+        .ok()
+        .and_then(std::char::from_u32)
+        .map(|character| LoxValue::String(character.to_string()))
+        .ok_or_else(|| {
+            error_in_line(
+                interpreter,
+                &format!("chr() {code_point} is not a valid code point."),
+            )
+        })
+}
+
+fn repeat(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let text = string(interpreter, "repeat", &arguments[0])?;
+    let count = index(interpreter, "repeat", &arguments[1])?;
+    match text.len().checked_mul(count) {
+        Some(length) if length <= MAX_REPEAT_LENGTH => Ok(LoxValue::String(text.repeat(count))),
+        _ => Err(error_in_line(
+            interpreter,
+            &format!("repeat() result would be longer than {MAX_REPEAT_LENGTH} bytes."),
+        )),
+    }
+}
@@ -0,0 +1,64 @@
+use super::{define, error_in_line, nth, string};
+use crate::environment::Environment;
+use crate::interpreter::Interpreter;
+use crate::value::{LoxError, LoxValue};
+use std::convert::TryFrom;
+
+pub fn define_globals(global: &mut Environment) {
+    define(global, "args", 1, args);
+    define(global, "args_count", 0, args_count);
+    define(global, "getenv", 1, getenv);
+    define(global, "setenv", 2, setenv);
+    define(global, "exit", 1, exit);
+}
+
+// `args(n)` gives the n-th argument passed after the script's path.
+fn args(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    nth(
+        interpreter,
+        "args",
+        interpreter.args().iter().cloned(),
+        &arguments[0],
+    )
+}
+
+#[allow(clippy::unnecessary_wraps)]
+fn args_count(interpreter: &mut Interpreter, _: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let count = i64::try_from(interpreter.args().len()).unwrap_or(i64::MAX);
+    Ok(LoxValue::Integer(count))
+}
+
+// The variable's value, or nil when it isn't set or isn't valid Unicode.
+fn getenv(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let name = string(interpreter, "getenv", &arguments[0])?;
+    Ok(std::env::var(name).map_or(LoxValue::Nil, LoxValue::String))
+}
+
+fn setenv(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    let name = string(interpreter, "setenv", &arguments[0])?;
+    let value = string(interpreter, "setenv", &arguments[1])?;
+    // `std::env::set_var` panics on these
+    if name.is_empty() || name.contains('=') || name.contains('\0') || value.contains('\0') {
+        return Err(error_in_line(
+            interpreter,
+            &format!("setenv() invalid environment variable '{name}'."),
+        ));
+    }
+    std::env::set_var(name, value);
+    Ok(LoxValue::Nil)
+}
+
+// Ends the program by unwinding to whoever runs it, which exits with the code.
+fn exit(interpreter: &mut Interpreter, arguments: &[LoxValue]) -> Result<LoxValue, LoxError> {
+    match &arguments[0] {
+        // the codes a process can exit with everywhere
+        LoxValue::Integer(code) => match u8::try_from(*code) {
+            Ok(code) => Err(LoxError::Exit(i32::from(code))),
+            Err(_) => Err(error_in_line(
+                interpreter,
+                &format!("exit() code {code} is out of range."),
+            )),
+        },
+        _ => Err(error_in_line(interpreter, "exit() expects an integer.")),
+    }
+}
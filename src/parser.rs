@@ -1,26 +1,50 @@
 use crate::expression::Expr;
-use crate::statement::{FuncStmt, Stmt};
+use crate::statement::{FuncStmt, Stmt, StmtKind};
 use crate::token::Token;
 use crate::token::TokenType;
 use crate::token::TokenType::{
-    And, Bang, BangEqual, Comma, Else, Equal, EqualEqual, False, For, Fun, Greater, GreaterEqual,
-    Identifier, If, LeftBrace, LeftParen, Less, LessEqual, Minus, Nil, Number, Or, Plus, Print,
-    Return, RightBrace, RightParen, Semicolon, Slash, Star, True, Var, While, EOF,
+    And, Bang, BangEqual, Colon, Comma, Const, Else, Equal, EqualEqual, False, For, Fun, Greater,
+    GreaterEqual, Identifier, If, Integer, LeftBrace, LeftParen, Less, LessEqual, Minus, Nil,
+    Number, Or, Plus, Print, Question, Return, RightBrace, RightParen, Semicolon, Slash, Star,
+    True, Var, While, EOF,
 };
 use crate::value::LoxError;
 use std::mem::discriminant;
 
+// How deeply statements and expressions may nest. Every pass over the tree
+// recurses, so deeper programs are rejected instead of overflowing the stack.
+const MAX_DEPTH: usize = 200;
+// How many operators of chains like `a + b + c` may lie on the way down to an
+// expression. A chain nests to the left, one level per operator, but long flat
+// chains are common enough to get a budget of their own.
+const MAX_CHAIN: usize = 10_000;
+
 pub struct Parser<'a> {
     tokens: &'a [Token],
     current: usize,
+    depth: usize,
+    chain: usize,
 }
 
-impl<'a> Parser<'a> {
+impl Parser<'_> {
     pub fn parse(tokens: &[Token]) -> Result<Vec<Stmt>, Vec<LoxError>> {
-        let mut parser = Parser::new(tokens);
+        let mut parser = Parser::new(tokens)?;
         parser.parse_statements().map_err(|err| vec![err])
     }
 
+    pub fn parse_expression(tokens: &[Token]) -> Result<Expr, Vec<LoxError>> {
+        let mut parser = Parser::new(tokens)?;
+        let expression = parser.expression().map_err(|err| vec![err])?;
+        if parser.is_at_end() {
+            Ok(expression)
+        } else {
+            Err(vec![Self::format_error(
+                parser.peek(),
+                "Expected end of expression.",
+            )])
+        }
+    }
+
     pub fn parse_statements(&mut self) -> Result<Vec<Stmt>, LoxError> {
         let mut statements: Vec<Stmt> = vec![];
         while !self.is_at_end() {
@@ -29,49 +53,87 @@ impl<'a> Parser<'a> {
         Ok(statements)
     }
 
-    fn new(tokens: &[Token]) -> Parser {
-        Parser { tokens, current: 0 }
+    fn new(tokens: &[Token]) -> Result<Parser<'_>, Vec<LoxError>> {
+        if let Some(Token {
+            token_type: EOF, ..
+        }) = tokens.last()
+        {
+            Ok(Parser {
+                tokens,
+                current: 0,
+                depth: 0,
+                chain: 0,
+            })
+        } else {
+            Err(vec![LoxError::Standard(
+                "Parser bug: the tokens don't end with EOF.".to_string(),
+            )])
+        }
     }
 }
 
 // Expressions
 impl<'a> Parser<'a> {
     fn expression(&mut self) -> Result<Expr, LoxError> {
-        self.assignment()
+        self.nested(Parser::assignment)
     }
 
     fn assignment(&mut self) -> Result<Expr, LoxError> {
-        let expr = self.logic_or()?;
+        let expr = self.conditional()?;
         if self.match_type(&[Equal]) {
             let equals = self.previous().clone();
-            let value = self.assignment()?;
+            let value = self.nested(Parser::assignment)?;
             if let Expr::Variable(token) = expr {
                 return Ok(Expr::Assignment(token, Box::from(value)));
             }
-            return Err(self.format_error(&equals, "Invalid assignment target."));
+            return Err(Self::format_error(&equals, "Invalid assignment target."));
         }
         Ok(expr)
     }
 
+    fn conditional(&mut self) -> Result<Expr, LoxError> {
+        let condition = self.logic_or()?;
+        if self.match_type(&[Question]) {
+            let then_branch = self.expression()?;
+            self.consume(
+                &Colon,
+                "Expected ':' after then branch of conditional expression.",
+            )?;
+            let else_branch = self.nested(Parser::conditional)?;
+            return Ok(Expr::Conditional(
+                Box::from(condition),
+                Box::from(then_branch),
+                Box::from(else_branch),
+            ));
+        }
+        Ok(condition)
+    }
+
     fn logic_or(&mut self) -> Result<Expr, LoxError> {
+        let chain = self.chain;
         let mut expr = self.logic_and()?;
 
         while self.match_type(&[Or]) {
             let operator = self.previous().clone();
+            self.longer_chain()?;
             let right = self.logic_and()?;
             expr = Expr::Logical(Box::from(expr), operator, Box::from(right));
         }
+        self.chain = chain;
         Ok(expr)
     }
 
     fn logic_and(&mut self) -> Result<Expr, LoxError> {
+        let chain = self.chain;
         let mut expr = self.equality()?;
 
         while self.match_type(&[And]) {
             let operator = self.previous().clone();
+            self.longer_chain()?;
             let right = self.equality()?;
             expr = Expr::Logical(Box::from(expr), operator, Box::from(right));
         }
+        self.chain = chain;
         Ok(expr)
     }
 
@@ -95,23 +157,26 @@ impl<'a> Parser<'a> {
         if self.match_type(&[Bang, Minus]) {
             let operator = self.previous().clone();
             return self
-                .unary()
+                .nested(Parser::unary)
                 .map(|right| Expr::Unary(operator, Box::from(right)));
         }
         self.call()
     }
 
     fn call(&mut self) -> Result<Expr, LoxError> {
+        let depth = self.depth;
         let mut expr = self.primary()?;
 
         loop {
             if self.match_type(&[LeftParen]) {
+                self.deeper()?;
                 expr = self.finish_call(expr)?;
             } else {
                 break;
             }
         }
 
+        self.depth = depth;
         Ok(expr)
     }
 
@@ -121,9 +186,10 @@ impl<'a> Parser<'a> {
         if !self.check(&RightParen) {
             loop {
                 if arguments.len() >= 255 {
-                    return Err(
-                        self.format_error(self.peek(), "Cannot have more than 255 arguments")
-                    );
+                    return Err(Self::format_error(
+                        self.peek(),
+                        "Cannot have more than 255 arguments",
+                    ));
                 }
                 arguments.push(self.expression()?);
                 if !self.match_type(&[Comma]) {
@@ -136,22 +202,15 @@ impl<'a> Parser<'a> {
     }
 
     fn primary(&mut self) -> Result<Expr, LoxError> {
-        if self.match_type(&[False]) {
-            return Ok(Expr::False);
-        }
-        if self.match_type(&[True]) {
-            return Ok(Expr::True);
-        }
-        if self.match_type(&[Nil]) {
-            return Ok(Expr::Nil);
-        }
-
-        if self.match_type(&[Number(0.0), TokenType::String(String::from(""))]) {
-            return match &self.previous().token_type {
-                Number(num) => Ok(Expr::Number(*num)),
-                TokenType::String(string) => Ok(Expr::String(string.clone())),
-                _ => panic!(),
-            };
+        if self.match_type(&[
+            False,
+            True,
+            Nil,
+            Number(0.0),
+            Integer(0),
+            TokenType::String(String::new()),
+        ]) {
+            return Ok(Expr::Literal(self.previous().clone()));
         }
 
         if self.match_type(&[LeftParen]) {
@@ -160,11 +219,11 @@ impl<'a> Parser<'a> {
             return Ok(Expr::Grouping(Box::from(expr)));
         }
 
-        if self.match_type(&[Identifier("".to_string())]) {
+        if self.match_type(&[Identifier(String::new())]) {
             return Ok(Expr::Variable(self.previous().clone()));
         }
 
-        Err(self.format_error(self.peek(), "Expected expression."))
+        Err(Self::format_error(self.peek(), "Expected expression."))
     }
 
     fn parse_binary_expression<F>(
@@ -175,17 +234,53 @@ impl<'a> Parser<'a> {
     where
         F: FnMut(&mut Parser<'a>) -> Result<Expr, LoxError>,
     {
+        let chain = self.chain;
         let mut expr = subexpression(self)?;
 
         while self.match_type(token_types) {
             let operator = self.previous().clone();
+            self.longer_chain()?;
             let right = subexpression(self)?;
             expr = Expr::Binary(Box::from(expr), operator, Box::from(right));
         }
 
+        self.chain = chain;
         Ok(expr)
     }
 
+    // Runs `parse` one level deeper in the tree.
+    fn nested<T, F>(&mut self, parse: F) -> Result<T, LoxError>
+    where
+        F: FnOnce(&mut Parser<'a>) -> Result<T, LoxError>,
+    {
+        self.deeper()?;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn deeper(&mut self) -> Result<(), LoxError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(Self::format_error(
+                self.peek(),
+                "Too deeply nested, simplify the program.",
+            ));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn longer_chain(&mut self) -> Result<(), LoxError> {
+        if self.chain >= MAX_CHAIN {
+            return Err(Self::format_error(
+                self.peek(),
+                "Too many operators in one expression, split it up.",
+            ));
+        }
+        self.chain += 1;
+        Ok(())
+    }
+
     fn match_type(&mut self, types: &[TokenType]) -> bool {
         for token_type in types {
             if self.check(token_type) {
@@ -207,70 +302,87 @@ impl<'a> Parser<'a> {
         if !self.is_at_end() {
             self.current += 1;
         }
-        &self.previous()
+        self.previous()
     }
 
     fn is_at_end(&self) -> bool {
         discriminant(&self.peek().token_type) == discriminant(&EOF)
     }
 
+    // Past the end this keeps returning the last token, which the scanner
+    // always makes an EOF.
     fn peek(&self) -> &Token {
-        &self.tokens[self.current]
+        &self.tokens[self.current.min(self.tokens.len() - 1)]
     }
 
     fn previous(&self) -> &Token {
-        &self.tokens[self.current - 1]
+        &self.tokens[self.current.saturating_sub(1).min(self.tokens.len() - 1)]
     }
 
     fn consume(&mut self, token_type: &TokenType, message: &str) -> Result<Token, LoxError> {
         if self.check(token_type) {
             return Ok(self.advance().clone());
         }
-        Err(self.format_error(self.peek(), message))
+        Err(Self::format_error(self.peek(), message))
     }
 
-    fn format_error(&self, token: &Token, message: &str) -> LoxError {
-        if let EOF = &token.token_type {
-            LoxError::Standard(format!(
-                "Unexpected EOF at line {}. {} ",
-                token.line, message
-            ))
+    fn format_error(token: &Token, message: &str) -> LoxError {
+        let message = if let EOF = &token.token_type {
+            format!("Unexpected EOF at line {}. {} ", token.line, message)
         } else {
-            LoxError::Standard(format!(
-                "{:?} at line {}. {}",
-                token.token_type, token.line, message
-            ))
+            format!("{:?} at line {}. {}", token.token_type, token.line, message)
+        };
+        LoxError::Static {
+            line: token.line,
+            message,
         }
     }
 }
 
 // statements
-impl<'a> Parser<'a> {
+impl Parser<'_> {
     fn declaration(&mut self) -> Result<Stmt, LoxError> {
-        if self.match_type(&[Fun]) {
-            return self.function_declaration("function");
-        }
-        if self.match_type(&[Var]) {
-            return self.var_declaration();
+        let (line, column) = (self.peek().line, self.peek().column);
+        let kind = if self.match_type(&[Fun]) {
+            self.nested(|parser| parser.function_declaration("function"))?
+        } else if self.match_type(&[Var]) {
+            self.var_declaration()?
+        } else if self.match_type(&[Const]) {
+            self.const_declaration()?
+        } else {
+            return self.statement();
+        };
+        Ok(self.spanned(line, column, kind))
+    }
+
+    // Wraps a statement that started at `line` and `column` and ends with the
+    // previous token.
+    fn spanned(&self, line: usize, column: usize, kind: StmtKind) -> Stmt {
+        Stmt {
+            kind,
+            line,
+            column,
+            end_line: self.previous().line,
         }
-        self.statement()
     }
 
-    fn function_declaration(&mut self, kind: &str) -> Result<Stmt, LoxError> {
+    fn function_declaration(&mut self, kind: &str) -> Result<StmtKind, LoxError> {
+        let doc = self.previous().doc.clone();
         let name = self.consume(
-            &Identifier("".to_string()),
-            &format! {"Expected {} name", kind},
+            &Identifier(String::new()),
+            &format! {"Expected {kind} name"},
         )?;
-        self.consume(&LeftParen, &format! {"Expected '(' after {} name", kind})?;
+        self.consume(&LeftParen, &format! {"Expected '(' after {kind} name"})?;
         let mut params: Vec<Token> = vec![];
         if !self.check(&RightParen) {
             loop {
                 if params.len() >= 255 {
-                    return Err(
-                        self.format_error(self.peek(), "Cannot have more than 255 parameters.")
-                    );
+                    return Err(Self::format_error(
+                        self.peek(),
+                        "Cannot have more than 255 parameters.",
+                    ));
                 }
-                params.push(self.consume(&Identifier("".to_string()), "Expected parameter name.")?);
+                params.push(self.consume(&Identifier(String::new()), "Expected parameter name.")?);
                 if !self.match_type(&[Comma]) {
                     break;
                 }
@@ -278,45 +390,58 @@ impl<'a> Parser<'a> {
         }
         self.consume(&RightParen, "Expected ')' after parameters")?;
 
-        self.consume(&LeftBrace, &format!("Expected '{{' before {} body.", kind))?;
+        self.consume(&LeftBrace, &format!("Expected '{{' before {kind} body."))?;
         let body = self.block()?;
-        Ok(Stmt::Function(FuncStmt { name, params, body }))
+        Ok(StmtKind::Function(FuncStmt {
+            name,
+            params,
+            body,
+            doc,
+        }))
     }
 
-    fn var_declaration(&mut self) -> Result<Stmt, LoxError> {
-        let name = self.consume(&Identifier("".to_string()), "Expected variable name")?;
+    fn var_declaration(&mut self) -> Result<StmtKind, LoxError> {
+        let name = self.consume(&Identifier(String::new()), "Expected variable name")?;
         let initializer = if self.match_type(&[Equal]) {
             Some(self.expression()?)
         } else {
             Option::None
         };
         self.consume(&Semicolon, "Expected ';' after variable declaration")?;
-        Ok(Stmt::Var(name, initializer))
+        Ok(StmtKind::Var(name, initializer))
+    }
+
+    fn const_declaration(&mut self) -> Result<StmtKind, LoxError> {
+        let name = self.consume(&Identifier(String::new()), "Expected constant name")?;
+        self.consume(&Equal, "Expected '=' after constant name")?;
+        let initializer = self.expression()?;
+        self.consume(&Semicolon, "Expected ';' after constant declaration")?;
+        Ok(StmtKind::Const(name, initializer))
     }
 
     fn statement(&mut self) -> Result<Stmt, LoxError> {
-        if self.match_type(&[If]) {
-            return self.if_statement();
-        }
-        if self.match_type(&[Print]) {
-            return self.print_statement();
-        }
-        if self.match_type(&[LeftBrace]) {
-            return self.block().map(Stmt::Block);
-        }
-        if self.match_type(&[While]) {
-            return self.while_statement();
-        }
-        if self.match_type(&[For]) {
-            return self.for_statement();
-        }
-        if self.match_type(&[Return]) {
-            return self.return_statement();
-        }
-        self.expression_statement()
+        let (line, column) = (self.peek().line, self.peek().column);
+        let kind = self.nested(|parser| {
+            if parser.match_type(&[If]) {
+                parser.if_statement()
+            } else if parser.match_type(&[Print]) {
+                parser.print_statement()
+            } else if parser.match_type(&[LeftBrace]) {
+                parser.block().map(StmtKind::Block)
+            } else if parser.match_type(&[While]) {
+                parser.while_statement()
+            } else if parser.match_type(&[For]) {
+                parser.for_statement()
+            } else if parser.match_type(&[Return]) {
+                parser.return_statement()
+            } else {
+                parser.expression_statement()
+            }
+        })?;
+        Ok(self.spanned(line, column, kind))
     }
 
-    fn if_statement(&mut self) -> Result<Stmt, LoxError> {
+    fn if_statement(&mut self) -> Result<StmtKind, LoxError> {
         self.consume(&LeftParen, "Expected '(' after 'if'.")?;
         let condition = self.expression()?;
         self.consume(&RightParen, "Expected ')' after if condition.")?;
@@ -328,13 +453,13 @@ impl<'a> Parser<'a> {
             Option::None
         };
 
-        Ok(Stmt::If(condition, then_branch, else_branch))
+        Ok(StmtKind::If(condition, then_branch, else_branch))
     }
 
-    fn print_statement(&mut self) -> Result<Stmt, LoxError> {
+    fn print_statement(&mut self) -> Result<StmtKind, LoxError> {
         let value = self.expression()?;
         self.consume(&Semicolon, "Expected ';' after value.")?;
-        Ok(Stmt::Print(value))
+        Ok(StmtKind::Print(value))
     }
 
     fn block(&mut self) -> Result<Vec<Stmt>, LoxError> {
@@ -346,63 +471,60 @@ impl<'a> Parser<'a> {
         Ok(statements)
     }
 
-    fn while_statement(&mut self) -> Result<Stmt, LoxError> {
+    fn while_statement(&mut self) -> Result<StmtKind, LoxError> {
         self.consume(&LeftParen, "Expected '(' after 'while'.")?;
         let condition = self.expression()?;
         self.consume(&RightParen, "Expected ')' after while condition.")?;
 
         let body = Box::from(self.statement()?);
-        Ok(Stmt::While(condition, body))
+        Ok(StmtKind::While(condition, body))
     }
 
-    fn for_statement(&mut self) -> Result<Stmt, LoxError> {
+    fn for_statement(&mut self) -> Result<StmtKind, LoxError> {
         self.consume(&LeftParen, "Expected '(' after 'for'.")?;
 
-        let mut statements: Vec<Stmt> = vec![];
-        if !self.match_type(&[Semicolon]) {
-            if self.match_type(&[Var]) {
-                statements.push(self.var_declaration()?);
-            } else {
-                statements.push(self.expression_statement()?);
-            }
-        }
+        let (line, column) = (self.peek().line, self.peek().column);
+        let initializer = if self.match_type(&[Semicolon]) {
+            None
+        } else if self.match_type(&[Var]) {
+            let kind = self.var_declaration()?;
+            Some(Box::from(self.spanned(line, column, kind)))
+        } else {
+            let kind = self.expression_statement()?;
+            Some(Box::from(self.spanned(line, column, kind)))
+        };
 
         let condition = if self.check(&Semicolon) {
-            Expr::True
+            None
         } else {
-            self.expression()?
+            Some(self.expression()?)
         };
         self.consume(&Semicolon, "Expected ';' after loop condition.")?;
 
         let increment = if self.check(&RightParen) {
-            Option::None
+            None
         } else {
-            Option::from(Stmt::Expr(self.expression()?))
+            Some(self.expression()?)
         };
         self.consume(&RightParen, "Expected ')' after 'for'.")?;
 
-        let mut body = self.statement()?;
-        if let Some(increment) = increment {
-            body = Stmt::Block(vec![body, increment]);
-        }
-        statements.push(Stmt::While(condition, Box::from(body)));
-
-        Ok(Stmt::Block(statements))
+        let body = Box::from(self.statement()?);
+        Ok(StmtKind::For(initializer, condition, increment, body))
     }
 
-    fn expression_statement(&mut self) -> Result<Stmt, LoxError> {
+    fn expression_statement(&mut self) -> Result<StmtKind, LoxError> {
         let expression = self.expression()?;
         self.consume(&Semicolon, "Expected ';' after expression.")?;
-        Ok(Stmt::Expr(expression))
+        Ok(StmtKind::Expr(expression))
     }
 
-    fn return_statement(&mut self) -> Result<Stmt, LoxError> {
+    fn return_statement(&mut self) -> Result<StmtKind, LoxError> {
         let value = if self.check(&Semicolon) {
             None
         } else {
             Some(self.expression()?)
         };
         self.consume(&Semicolon, "Expected ';' after return value.")?;
-        Ok(Stmt::Ret(value))
+        Ok(StmtKind::Ret(value))
     }
 }
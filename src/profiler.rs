@@ -0,0 +1,305 @@
+use crate::interpreter::{Frame, Hook, Interpreter};
+use crate::statement::Stmt;
+use crate::value::LoxError;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// A function by name and the line it is defined on, line 0 is the script.
+type Function = (String, usize);
+
+#[derive(Default)]
+struct FunctionStats {
+    calls: usize,
+    // time spent in the function and everything it called
+    total: Duration,
+    // time spent running the function's own statements
+    own: Duration,
+}
+
+#[derive(Default)]
+struct LineStats {
+    executions: usize,
+    time: Duration,
+}
+
+// Times and counts collected while the program runs. The time between two
+// events is charged to the call stack and line that were current at the first.
+pub struct Profile {
+    functions: HashMap<Function, FunctionStats>,
+    lines: HashMap<usize, LineStats>,
+    stacks: HashMap<Vec<Function>, Duration>,
+    stack: Vec<Function>,
+    line: usize,
+    since: Instant,
+}
+
+impl Profile {
+    pub fn new() -> Rc<RefCell<Profile>> {
+        let script = ("<script>".to_string(), 0);
+        let mut functions = HashMap::new();
+        functions.insert(
+            script.clone(),
+            FunctionStats {
+                calls: 1,
+                ..FunctionStats::default()
+            },
+        );
+        Rc::new(RefCell::new(Profile {
+            functions,
+            lines: HashMap::new(),
+            stacks: HashMap::new(),
+            stack: vec![script],
+            line: 0,
+            since: Instant::now(),
+        }))
+    }
+
+    // Charges the time since the last event and makes `frames` current.
+    fn record(&mut self, frames: &[Frame]) {
+        let now = Instant::now();
+        let elapsed = now - self.since;
+        self.since = now;
+
+        *self.stacks.entry(self.stack.clone()).or_default() += elapsed;
+        if self.line > 0 {
+            self.lines.entry(self.line).or_default().time += elapsed;
+        }
+        if let Some(innermost) = self.stack.last() {
+            self.functions.entry(innermost.clone()).or_default().own += elapsed;
+        }
+        // a recursive function is only charged once for the same time
+        let mut charged = Vec::new();
+        for function in &self.stack {
+            if !charged.contains(&function) {
+                self.functions.entry(function.clone()).or_default().total += elapsed;
+                charged.push(function);
+            }
+        }
+
+        self.stack = frames
+            .iter()
+            .map(|frame| (frame.name.clone(), frame.definition_line))
+            .collect();
+        self.line = frames.last().map_or(0, |frame| frame.line);
+    }
+
+    // Charges the time up to the end of the program.
+    pub fn finish(&mut self) {
+        self.record(&[]);
+    }
+
+    // Functions by total time, then lines by time, slowest first.
+    pub fn summary(&self) -> String {
+        let mut functions: Vec<_> = self.functions.iter().collect();
+        functions.sort_by(|(left_name, left), (right_name, right)| {
+            right.total.cmp(&left.total).then(left_name.cmp(right_name))
+        });
+        let mut output = String::new();
+        let _ = writeln!(
+            output,
+            "{:>12} {:>12} {:>8}  function",
+            "total ms", "self ms", "calls"
+        );
+        for (function, stats) in functions {
+            let _ = writeln!(
+                output,
+                "{:>12.3} {:>12.3} {:>8}  {}",
+                milliseconds(stats.total),
+                milliseconds(stats.own),
+                stats.calls,
+                label(function)
+            );
+        }
+
+        let mut lines: Vec<_> = self.lines.iter().collect();
+        lines.sort_by(|(left_line, left), (right_line, right)| {
+            right.time.cmp(&left.time).then(left_line.cmp(right_line))
+        });
+        let _ = writeln!(output);
+        let _ = writeln!(output, "{:>12} {:>12}  line", "time ms", "executions");
+        for (line, stats) in lines {
+            let _ = writeln!(
+                output,
+                "{:>12.3} {:>12}  {}",
+                milliseconds(stats.time),
+                stats.executions,
+                line
+            );
+        }
+        output
+    }
+
+    // One line per call stack in the collapsed format read by flame graph
+    // tools, with the time in microseconds.
+    pub fn collapsed_stacks(&self) -> String {
+        let mut stacks: Vec<_> = self
+            .stacks
+            .iter()
+            .filter(|(stack, _)| !stack.is_empty())
+            .map(|(stack, time)| {
+                let names = stack.iter().map(label).collect::<Vec<_>>().join(";");
+                (names, time.as_micros())
+            })
+            .filter(|(_, micros)| *micros > 0)
+            .collect();
+        stacks.sort();
+        let mut output = String::new();
+        for (names, micros) in stacks {
+            let _ = writeln!(output, "{names} {micros}");
+        }
+        output
+    }
+}
+
+fn label((name, line): &Function) -> String {
+    if *line == 0 {
+        name.clone()
+    } else {
+        format!("{name}:{line}")
+    }
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+// Records into a profile shared with whoever reports it once the program ends.
+pub struct Profiler {
+    profile: Rc<RefCell<Profile>>,
+}
+
+impl Profiler {
+    pub fn new(profile: &Rc<RefCell<Profile>>) -> Profiler {
+        Profiler {
+            profile: Rc::clone(profile),
+        }
+    }
+}
+
+impl Hook for Profiler {
+    fn before_statement(
+        &mut self,
+        interpreter: &mut Interpreter,
+        statement: &Stmt,
+    ) -> Result<(), LoxError> {
+        let mut profile = self.profile.borrow_mut();
+        profile.record(interpreter.frames());
+        profile.lines.entry(statement.line).or_default().executions += 1;
+        Ok(())
+    }
+
+    fn enter_function(&mut self, frames: &[Frame]) {
+        let mut profile = self.profile.borrow_mut();
+        profile.record(frames);
+        if let Some(frame) = frames.last() {
+            let function = (frame.name.clone(), frame.definition_line);
+            profile.functions.entry(function).or_default().calls += 1;
+        }
+    }
+
+    fn exit_function(&mut self, frames: &[Frame]) {
+        // time from here on belongs to the caller
+        self.profile
+            .borrow_mut()
+            .record(&frames[..frames.len().saturating_sub(1)]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::tests::run_with_hook;
+
+    fn profile(source: &str) -> Rc<RefCell<Profile>> {
+        let profile = Profile::new();
+        run_with_hook(source, |_| Box::new(Profiler::new(&profile)));
+        profile.borrow_mut().finish();
+        profile
+    }
+
+    fn function(name: &str, line: usize) -> Function {
+        (name.to_string(), line)
+    }
+
+    #[test]
+    fn counts_calls_and_line_executions() {
+        let source = "fun f(n) {\n  return n + 1;\n}\nfor (var i = 0; i < 3; i = i + 1)\n  f(i);\n";
+        let profile = profile(source);
+        let profile = profile.borrow();
+        assert_eq!(profile.functions[&function("f", 1)].calls, 3);
+        assert_eq!(profile.functions[&function("<script>", 0)].calls, 1);
+        assert_eq!(profile.lines[&2].executions, 3);
+        assert_eq!(profile.lines[&5].executions, 3);
+        assert!(profile
+            .stacks
+            .contains_key(&vec![function("<script>", 0), function("f", 1)]));
+    }
+
+    #[test]
+    fn charges_recursive_calls_once() {
+        let source = "fun count(n) {\n  if (n > 0) count(n - 1);\n}\ncount(50);\n";
+        let profile = profile(source);
+        let profile = profile.borrow();
+        let count = &profile.functions[&function("count", 1)];
+        assert_eq!(count.calls, 51);
+        assert!(count.total <= profile.functions[&function("<script>", 0)].total);
+        assert!(count.own <= count.total);
+    }
+
+    #[test]
+    fn writes_collapsed_stacks() {
+        let profile = Profile::new();
+        let mut profile = profile.borrow_mut();
+        let script = function("<script>", 0);
+        profile.stacks.clear();
+        profile
+            .stacks
+            .insert(vec![script.clone()], Duration::from_micros(30));
+        profile.stacks.insert(
+            vec![script.clone(), function("f", 1)],
+            Duration::from_micros(12),
+        );
+        profile
+            .stacks
+            .insert(vec![script, function("g", 4)], Duration::from_nanos(10));
+        profile.stacks.insert(Vec::new(), Duration::from_micros(5));
+        assert_eq!(profile.collapsed_stacks(), "<script> 30\n<script>;f:1 12\n");
+    }
+
+    #[test]
+    fn summary_lists_the_slowest_first() {
+        let profile = Profile::new();
+        let mut profile = profile.borrow_mut();
+        profile.functions.insert(
+            function("f", 1),
+            FunctionStats {
+                calls: 2,
+                total: Duration::from_millis(3),
+                own: Duration::from_millis(1),
+            },
+        );
+        profile.lines.insert(
+            2,
+            LineStats {
+                executions: 4,
+                time: Duration::from_millis(2),
+            },
+        );
+        let summary = profile.summary();
+        let lines: Vec<&str> = summary.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "    total ms      self ms    calls  function",
+                "       3.000        1.000        2  f:1",
+                "       0.000        0.000        1  <script>",
+                "",
+                "     time ms   executions  line",
+                "       2.000            4  2",
+            ]
+        );
+    }
+}
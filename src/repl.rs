@@ -0,0 +1,240 @@
+use crate::Lox;
+use rlox::ast_printer;
+use rlox::environment::Environment;
+use rlox::interpreter::Interpreter;
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+use rlox::statement::{Stmt, StmtKind};
+use rlox::token::KEYWORDS;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::Instant;
+use unicode_xid::UnicodeXID;
+
+const COMMANDS: &[(&str, &str)] = &[
+    (":env", "list the global bindings"),
+    (":load <file>", "run a file in the current session"),
+    (":reset", "discard every definition and start over"),
+    (
+        ":ast <code>",
+        "show the syntax tree of an expression or statements",
+    ),
+    (":tokens <code>", "show the tokens scanned from the code"),
+    (":time <code>", "run the code and report how long it took"),
+    (":help", "show this message"),
+    (":quit", "leave the prompt"),
+];
+
+const HISTORY_FILE: &str = ".rlox_history";
+
+type LoxEditor = Editor<LoxHelper, DefaultHistory>;
+
+pub fn run(lox: &mut Lox) {
+    let mut editor = match LoxEditor::new() {
+        Ok(editor) => editor,
+        Err(error) => {
+            println!("Error: unable to start the prompt: {error}");
+            return;
+        }
+    };
+    editor.set_helper(Some(LoxHelper {
+        global: Rc::clone(&lox.interpreter.global),
+    }));
+    let history = history_path();
+    if let Some(history) = &history {
+        // there is no history to load the first time the prompt is used
+        let _ = editor.load_history(history);
+    }
+
+    let mut input = String::new();
+    loop {
+        let prompt = if input.is_empty() { "> " } else { "... " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(error) => {
+                println!("Error: unable to read user input: {error}");
+                break;
+            }
+        };
+
+        if input.is_empty() && line.trim_start().starts_with(':') {
+            let _ = editor.add_history_entry(line.trim());
+            if !run_command(lox, &mut editor, line.trim()) {
+                break;
+            }
+            continue;
+        }
+
+        input.push_str(&line);
+        input.push('\n');
+        // a blank line submits the input even if it is still incomplete
+        if !line.trim().is_empty() && Scanner::is_incomplete(&input) {
+            continue;
+        }
+        if !input.trim().is_empty() {
+            let _ = editor.add_history_entry(input.trim_end());
+        }
+        lox.run_interactive(&input);
+        input.clear();
+        if lox.exit_code.is_some() {
+            break;
+        }
+    }
+
+    if let Some(history) = &history {
+        if let Err(error) = editor.save_history(history) {
+            println!("Error: unable to save history: {error}");
+        }
+    }
+    if let Some(code) = lox.exit_code {
+        std::process::exit(code);
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+// Runs a meta-command, returning false when the prompt should exit.
+fn run_command(lox: &mut Lox, editor: &mut LoxEditor, command: &str) -> bool {
+    let (name, argument) = command
+        .split_once(char::is_whitespace)
+        .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+    match name {
+        ":quit" | ":q" => return false,
+        ":help" => {
+            for (command, description) in COMMANDS {
+                println!("{command:<16}{description}");
+            }
+        }
+        ":env" => print_environment(&lox.interpreter.global.borrow()),
+        ":load" => match std::fs::read_to_string(argument) {
+            Ok(contents) => {
+                lox.run(&contents);
+            }
+            Err(error) => println!("Error: unable to read '{argument}': {error}"),
+        },
+        ":reset" => {
+            lox.interpreter = Interpreter::new();
+            if let Some(helper) = editor.helper_mut() {
+                helper.global = Rc::clone(&lox.interpreter.global);
+            }
+        }
+        ":ast" => print_ast(argument),
+        ":tokens" => print_tokens(argument),
+        ":time" => {
+            let start = Instant::now();
+            lox.run_interactive(argument);
+            println!("elapsed: {:?}", start.elapsed());
+        }
+        _ => println!("Error: unknown command '{name}', try :help"),
+    }
+    // `exit` called from a loaded file or timed code
+    lox.exit_code.is_none()
+}
+
+fn print_environment(environment: &Environment) {
+    let mut bindings: Vec<_> = environment.bindings().collect();
+    bindings.sort_by_key(|(name, ..)| *name);
+    for (name, value, mutable) in bindings {
+        let keyword = if mutable { "var" } else { "const" };
+        println!("{keyword} {name} = {value}");
+    }
+}
+
+fn print_ast(source: &str) {
+    let tokens = match Scanner::scan(source) {
+        Ok(tokens) => tokens,
+        Err(errors) => return Lox::report(&errors),
+    };
+    let statements = match Parser::parse_expression(&tokens) {
+        Ok(expression) => Ok(vec![Stmt {
+            line: expression.line(),
+            end_line: expression.line(),
+            column: expression.first_token().column,
+            kind: StmtKind::Expr(expression),
+        }]),
+        Err(_) => Parser::parse(&tokens),
+    };
+    match statements {
+        Ok(statements) => print!("{}", ast_printer::tree(&statements)),
+        Err(errors) => Lox::report(&errors),
+    }
+}
+
+fn print_tokens(source: &str) {
+    match Scanner::scan(source) {
+        Ok(tokens) => print!("{}", Lox::format_tokens(&tokens)),
+        Err(errors) => Lox::report(&errors),
+    }
+}
+
+struct LoxHelper {
+    global: Rc<RefCell<Environment>>,
+}
+
+impl Completer for LoxHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        if line.starts_with(':') && !line.contains(char::is_whitespace) {
+            let candidates = COMMANDS
+                .iter()
+                .filter_map(|(command, _)| command.split(' ').next())
+                .filter(|command| command.starts_with(line))
+                .map(str::to_string)
+                .collect();
+            return Ok((0, candidates));
+        }
+
+        let start = line
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !c.is_xid_continue())
+            .map_or(0, |(index, c)| index + c.len_utf8());
+        let prefix = &line[start..];
+        if prefix.is_empty() {
+            return Ok((pos, Vec::new()));
+        }
+
+        let global = self.global.borrow();
+        let mut candidates: Vec<String> = KEYWORDS
+            .iter()
+            .map(|keyword| (*keyword).to_string())
+            .chain(global.bindings().map(|(name, ..)| name.clone()))
+            .filter(|candidate| candidate.starts_with(prefix))
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        Ok((start, candidates))
+    }
+}
+
+impl Hinter for LoxHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LoxHelper {}
+
+impl Validator for LoxHelper {}
+
+impl Helper for LoxHelper {}
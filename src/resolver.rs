@@ -0,0 +1,170 @@
+use crate::environment::Environment;
+use crate::expression::Expr;
+use crate::statement::{Stmt, StmtKind};
+use crate::token::Token;
+use crate::token::TokenType;
+use crate::value::LoxError;
+use std::collections::HashMap;
+
+// Static pass run between parsing and interpreting. It tracks which names are
+// bound mutably in each lexical scope so that reassigning a constant can be
+// reported before any code runs.
+pub struct Resolver<'a> {
+    globals: &'a Environment,
+    scopes: Vec<HashMap<String, bool>>,
+    errors: Vec<LoxError>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn resolve(statements: &[Stmt], globals: &'a Environment) -> Result<(), Vec<LoxError>> {
+        let mut resolver = Resolver {
+            globals,
+            scopes: vec![HashMap::new()],
+            errors: Vec::new(),
+        };
+        resolver.resolve_statements(statements);
+        if resolver.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(resolver.errors)
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.resolve_statement(statement);
+        }
+    }
+
+    fn resolve_statement(&mut self, statement: &Stmt) {
+        match &statement.kind {
+            StmtKind::Block(statements) => {
+                self.scopes.push(HashMap::new());
+                self.resolve_statements(statements);
+                self.scopes.pop();
+            }
+            StmtKind::Expr(expression) | StmtKind::Print(expression) => {
+                self.resolve_expression(expression);
+            }
+            StmtKind::Function(func_stmt) => {
+                self.declare(&func_stmt.name, true);
+                self.scopes.push(HashMap::new());
+                for param in &func_stmt.params {
+                    self.declare(param, true);
+                }
+                self.resolve_statements(&func_stmt.body);
+                self.scopes.pop();
+            }
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.resolve_expression(condition);
+                self.resolve_statement(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_statement(else_branch);
+                }
+            }
+            StmtKind::Var(name, initializer) => {
+                if let Some(initializer) = initializer {
+                    self.resolve_expression(initializer);
+                }
+                self.declare(name, true);
+            }
+            StmtKind::Const(name, initializer) => {
+                self.resolve_expression(initializer);
+                self.declare(name, false);
+            }
+            StmtKind::While(condition, body) => {
+                self.resolve_expression(condition);
+                self.resolve_statement(body);
+            }
+            StmtKind::For(initializer, condition, increment, body) => {
+                self.scopes.push(HashMap::new());
+                if let Some(initializer) = initializer {
+                    self.resolve_statement(initializer);
+                }
+                for expression in condition.iter().chain(increment) {
+                    self.resolve_expression(expression);
+                }
+                self.resolve_statement(body);
+                self.scopes.pop();
+            }
+            StmtKind::Ret(value) => {
+                if let Some(value) = value {
+                    self.resolve_expression(value);
+                }
+            }
+        }
+    }
+
+    fn resolve_expression(&mut self, expression: &Expr) {
+        match expression {
+            Expr::Literal(_) | Expr::Variable(_) => {}
+            Expr::Grouping(expression) | Expr::Unary(_, expression) => {
+                self.resolve_expression(expression);
+            }
+            Expr::Binary(left, _, right) | Expr::Logical(left, _, right) => {
+                self.resolve_expression(left);
+                self.resolve_expression(right);
+            }
+            Expr::Assignment(name, value) => {
+                self.resolve_expression(value);
+                self.check_assignment(name);
+            }
+            Expr::Conditional(condition, then_branch, else_branch) => {
+                self.resolve_expression(condition);
+                self.resolve_expression(then_branch);
+                self.resolve_expression(else_branch);
+            }
+            Expr::Call(callee, _, arguments) => {
+                self.resolve_expression(callee);
+                for argument in arguments {
+                    self.resolve_expression(argument);
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, mutable: bool) {
+        if let TokenType::Identifier(identifier) = &name.token_type {
+            // shadowing a constant is only allowed in an inner scope
+            let constant = match self.scopes.as_slice() {
+                [global] => global
+                    .get(identifier)
+                    .map_or_else(|| self.globals.is_constant(identifier), |mutable| !mutable),
+                [.., scope] => scope.get(identifier) == Some(&false),
+                [] => false,
+            };
+            if constant {
+                self.errors.push(LoxError::Static {
+                    line: name.line,
+                    message: format!(
+                        "Error in line: {}, cannot redeclare constant '{}'.",
+                        name.line, identifier
+                    ),
+                });
+            }
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.insert(identifier.clone(), mutable);
+            }
+        }
+    }
+
+    fn check_assignment(&mut self, name: &Token) {
+        if let TokenType::Identifier(identifier) = &name.token_type {
+            let mutable = self
+                .scopes
+                .iter()
+                .rev()
+                .find_map(|scope| scope.get(identifier).copied())
+                .unwrap_or_else(|| !self.globals.is_constant(identifier));
+            if !mutable {
+                self.errors.push(LoxError::Static {
+                    line: name.line,
+                    message: format!(
+                        "Error in line: {}, cannot assign to constant '{}'.",
+                        name.line, identifier
+                    ),
+                });
+            }
+        }
+    }
+}
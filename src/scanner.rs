@@ -1,14 +1,23 @@
+use crate::token::Comment;
 use crate::token::Token;
 use crate::token::TokenType;
 use crate::value::LoxError;
+use unicode_normalization::UnicodeNormalization;
+use unicode_xid::UnicodeXID;
 
 pub struct Scanner {
     source: Vec<char>,
     tokens: Vec<Token>,
+    comments: Vec<Comment>,
     start: usize,
     current: usize,
     line: usize,
+    line_start: usize,
+    start_line: usize,
+    start_column: usize,
     errors: Vec<LoxError>,
+    doc: Option<String>,
+    unterminated: bool,
 }
 
 impl Scanner {
@@ -16,7 +25,18 @@ impl Scanner {
         let mut scanner = Scanner::new(source);
         scanner.scan_tokens();
         if scanner.errors.is_empty() {
-            Ok(scanner.tokens.to_vec())
+            Ok(scanner.tokens.clone())
+        } else {
+            Err(scanner.errors)
+        }
+    }
+
+    // Like `scan`, but also returns the comments, which are otherwise discarded.
+    pub fn scan_with_comments(source: &str) -> Result<(Vec<Token>, Vec<Comment>), Vec<LoxError>> {
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens();
+        if scanner.errors.is_empty() {
+            Ok((scanner.tokens, scanner.comments))
         } else {
             Err(scanner.errors)
         }
@@ -26,22 +46,42 @@ impl Scanner {
         Scanner {
             source: source.chars().collect(),
             tokens: Vec::new(),
+            comments: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            line_start: 0,
+            start_line: 1,
+            start_column: 1,
             errors: Vec::new(),
+            doc: None,
+            unterminated: false,
         }
     }
 
+    // Whether the source stops in the middle of a string, comment or bracketed
+    // construct, meaning more input is needed before it can be parsed.
+    pub fn is_incomplete(source: &str) -> bool {
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens();
+        let depth = scanner
+            .tokens
+            .iter()
+            .fold(0, |depth, token| match token.token_type {
+                TokenType::LeftParen | TokenType::LeftBrace => depth + 1,
+                TokenType::RightParen | TokenType::RightBrace => depth - 1,
+                _ => depth,
+            });
+        scanner.unterminated || depth > 0
+    }
+
     fn scan_tokens(&mut self) {
         while !self.is_at_end() {
-            self.start = self.current;
+            self.begin_token();
             self.scan_token();
         }
-        self.tokens.push(Token {
-            token_type: TokenType::EOF,
-            line: self.line,
-        });
+        self.begin_token();
+        self.add_token(TokenType::EOF);
     }
 
     fn is_at_end(&self) -> bool {
@@ -53,9 +93,9 @@ impl Scanner {
 
         let mut matches_equal = |equal, not_equal| {
             if self.match_char('=') {
-                self.add_token(equal)
+                self.add_token(equal);
             } else {
-                self.add_token(not_equal)
+                self.add_token(not_equal);
             }
         };
 
@@ -70,37 +110,53 @@ impl Scanner {
             '+' => self.add_token(TokenType::Plus),
             ';' => self.add_token(TokenType::Semicolon),
             '*' => self.add_token(TokenType::Star),
+            '?' => self.add_token(TokenType::Question),
+            ':' => self.add_token(TokenType::Colon),
             '!' => matches_equal(TokenType::BangEqual, TokenType::Bang),
             '=' => matches_equal(TokenType::EqualEqual, TokenType::Equal),
             '<' => matches_equal(TokenType::LessEqual, TokenType::Less),
             '>' => matches_equal(TokenType::GreaterEqual, TokenType::Greater),
             '/' => {
                 if self.match_char('/') {
-                    while self.peek() != '\n' && !self.is_at_end() {
-                        self.advance();
-                    }
+                    self.line_comment();
+                } else if self.match_char('*') {
+                    self.block_comment();
                 } else {
-                    self.add_token(TokenType::Slash)
+                    self.add_token(TokenType::Slash);
                 }
             }
             ' ' | '\r' | '\t' => (),
-            '\n' => self.line += 1,
+            '\n' => self.new_line(),
             '"' => self.string(),
             _ => {
                 if c.is_ascii_digit() {
-                    self.number()
-                } else if c.is_ascii_alphabetic() || c == '_' {
-                    self.identifier()
+                    self.number();
+                } else if c == '_' || c.is_xid_start() {
+                    self.identifier();
                 } else {
-                    self.errors.push(LoxError::Standard(format!(
-                        "unexpected character at line {}",
-                        self.line
-                    )));
+                    self.error(format!(
+                        "Error: unexpected character '{}' at line {}, column {}",
+                        c.escape_debug(),
+                        self.start_line,
+                        self.start_column
+                    ));
                 }
             }
         }
     }
 
+    // must be called right after consuming a '\n'
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
+    fn begin_token(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.current - self.line_start + 1;
+    }
+
     fn match_char(&mut self, expected: char) -> bool {
         if self.is_at_end() {
             return false;
@@ -121,7 +177,10 @@ impl Scanner {
     fn add_token(&mut self, token: TokenType) {
         self.tokens.push(Token {
             token_type: token,
-            line: self.line,
+            lexeme: self.source[self.start..self.current].iter().collect(),
+            line: self.start_line,
+            column: self.start_column,
+            doc: self.doc.take(),
         });
     }
 
@@ -143,19 +202,72 @@ impl Scanner {
         self.source[self.current + 1]
     }
 
+    fn line_comment(&mut self) {
+        // exactly three slashes make a doc comment, "////" and beyond are plain comments
+        let is_doc = self.peek() == '/' && self.peek_next() != '/';
+        while self.peek() != '\n' && !self.is_at_end() {
+            self.advance();
+        }
+        self.add_comment();
+        if is_doc {
+            let text = self.source[self.start + 3..self.current]
+                .iter()
+                .collect::<String>();
+            let text = text.strip_prefix(' ').unwrap_or(&text).trim_end();
+            self.doc = Some(match self.doc.take() {
+                Some(doc) => format!("{doc}\n{text}"),
+                None => text.to_string(),
+            });
+        }
+    }
+
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                self.unterminated = true;
+                self.error(format!(
+                    "Error: unterminated block comment '/*' at line {}, column {}",
+                    self.start_line, self.start_column
+                ));
+                return;
+            }
+            match self.advance() {
+                '\n' => self.new_line(),
+                '/' if self.match_char('*') => depth += 1,
+                '*' if self.match_char('/') => depth -= 1,
+                _ => {}
+            }
+        }
+        self.add_comment();
+    }
+
+    fn add_comment(&mut self) {
+        let trailing = self
+            .tokens
+            .last()
+            .is_some_and(|token| token.line == self.start_line);
+        self.comments.push(Comment {
+            text: self.source[self.start..self.current].iter().collect(),
+            line: self.start_line,
+            trailing,
+            next_token: self.tokens.len(),
+        });
+    }
+
     fn string(&mut self) {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            if self.advance() == '\n' {
+                self.new_line();
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            self.errors.push(LoxError::Standard(format!(
-                "Error: unterminated string at line {}",
-                self.line
-            )));
+            self.unterminated = true;
+            self.error(format!(
+                "Error: unterminated string '\"' at line {}, column {}",
+                self.start_line, self.start_column
+            ));
             return;
         }
 
@@ -163,41 +275,153 @@ impl Scanner {
 
         let value = self.source[self.start + 1..self.current - 1]
             .iter()
-            .cloned()
+            .copied()
             .collect::<String>();
         self.add_token(TokenType::String(value));
     }
 
     fn number(&mut self) {
-        while self.peek().is_ascii_digit() {
-            self.advance();
+        if self.current_char() == '0' {
+            let radix = match self.peek() {
+                'x' | 'X' => Some((16, "hexadecimal")),
+                'o' | 'O' => Some((8, "octal")),
+                'b' | 'B' => Some((2, "binary")),
+                _ => None,
+            };
+            if let Some((radix, name)) = radix {
+                self.advance();
+                self.radix_number(radix, name);
+                return;
+            }
         }
 
+        // rescan from the first digit so digit separators are validated uniformly
+        self.current = self.start;
+        let mut valid = self.digits(10);
+        let mut is_float = false;
         if self.peek() == '.' && self.peek_next().is_ascii_digit() {
+            is_float = true;
             self.advance();
-            while self.peek().is_ascii_digit() {
+            valid &= self.digits(10);
+        }
+        if self.peek() == 'e' || self.peek() == 'E' {
+            is_float = true;
+            self.advance();
+            if self.peek() == '+' || self.peek() == '-' {
                 self.advance();
             }
+            if !self.peek().is_ascii_digit() {
+                self.number_error("expected digits in exponent");
+                return;
+            }
+            valid &= self.digits(10);
+        }
+        if !valid {
+            self.number_error("'_' must separate two digits");
+            return;
+        }
+        if self.peek().is_alphanumeric() || self.peek() == '_' {
+            self.number_error(&format!("unexpected character '{}'", self.peek()));
+            return;
+        }
+
+        let string_value = self.number_digits(self.start);
+        if is_float {
+            match string_value.parse() {
+                Ok(value) => self.add_token(TokenType::Number(value)),
+                Err(_) => self.number_error("invalid float"),
+            }
+        } else if let Ok(value) = string_value.parse::<i64>() {
+            self.add_token(TokenType::Integer(value));
+        } else {
+            self.number_error("integer out of range");
+        }
+    }
+
+    fn radix_number(&mut self, radix: u32, name: &str) {
+        let digits_start = self.current;
+        if !self.peek().is_digit(radix) {
+            self.number_error(&format!("expected {name} digits"));
+            return;
+        }
+        if !self.digits(radix) {
+            self.number_error("'_' must separate two digits");
+            return;
+        }
+        if self.peek().is_alphanumeric() || self.peek() == '_' {
+            self.number_error(&format!(
+                "unexpected character '{}' in {} literal",
+                self.peek(),
+                name
+            ));
+            return;
+        }
+
+        let string_value = self.number_digits(digits_start);
+        if let Ok(value) = i64::from_str_radix(&string_value, radix) {
+            self.add_token(TokenType::Integer(value));
+        } else {
+            self.number_error("integer out of range");
+        }
+    }
+
+    // Consumes digits in the given radix along with '_' separators, returning
+    // whether every separator sits between two digits.
+    fn digits(&mut self, radix: u32) -> bool {
+        let digits_start = self.current;
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            self.advance();
+        }
+        let digits = &self.source[digits_start..self.current];
+        digits.first() != Some(&'_')
+            && digits.last() != Some(&'_')
+            && !digits.windows(2).any(|pair| pair == ['_', '_'])
+    }
+
+    fn number_digits(&self, from: usize) -> String {
+        self.source[from..self.current]
+            .iter()
+            .filter(|c| **c != '_')
+            .collect()
+    }
+
+    fn number_error(&mut self, message: &str) {
+        // skip the rest of the malformed literal so it doesn't produce further errors
+        while self.peek().is_alphanumeric() || self.peek() == '_' {
+            self.advance();
         }
-        let string_value = &self.source[self.start..self.current]
+        let literal = self.source[self.start..self.current]
             .iter()
             .collect::<String>();
-        let value: f64 = string_value.parse().unwrap();
-        self.add_token(TokenType::Number(value));
+        self.error(format!(
+            "Error: malformed number literal '{}' at line {}, column {}: {}",
+            literal, self.start_line, self.start_column, message
+        ));
+    }
+
+    // Records an error in the token being scanned.
+    fn error(&mut self, message: String) {
+        self.errors.push(LoxError::Static {
+            line: self.start_line,
+            message,
+        });
     }
 
     fn identifier(&mut self) {
-        while self.peek().is_ascii_alphanumeric() {
+        while self.peek().is_xid_continue() {
             self.advance();
         }
+        // NFKC normalization makes visually identical spellings the same name
         let value = &self.source[self.start..self.current]
             .iter()
+            .copied()
+            .nfkc()
             .collect::<String>();
 
         if let Some(token_type) = Scanner::default_identifier(value) {
             self.add_token(token_type);
         } else {
-            self.add_token(TokenType::Identifier(value.to_string()));
+            self.add_token(TokenType::Identifier(value.clone()));
         }
     }
 
@@ -205,6 +429,7 @@ impl Scanner {
         match identifier {
             "and" => Some(TokenType::And),
             "class" => Some(TokenType::Class),
+            "const" => Some(TokenType::Const),
             "else" => Some(TokenType::Else),
             "false" => Some(TokenType::False),
             "for" => Some(TokenType::For),
@@ -2,14 +2,26 @@ use crate::expression::Expr;
 use crate::token::Token;
 
 #[derive(Debug, Clone)]
-pub enum Stmt {
+pub struct Stmt {
+    pub kind: StmtKind,
+    // lines of the first and last token of the statement
+    pub line: usize,
+    pub end_line: usize,
+    // column of the first token, with `line` it tells apart statements on one line
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum StmtKind {
     Block(Vec<Stmt>),
     Expr(Expr),
     Function(FuncStmt),
     If(Expr, Box<Stmt>, Option<Box<Stmt>>),
     Print(Expr),
     Var(Token, Option<Expr>),
+    Const(Token, Expr),
     While(Expr, Box<Stmt>),
+    For(Option<Box<Stmt>>, Option<Expr>, Option<Expr>, Box<Stmt>),
     Ret(Option<Expr>),
 }
 
@@ -18,4 +30,5 @@ pub struct FuncStmt {
     pub name: Token,
     pub params: Vec<Token>,
     pub body: Vec<Stmt>,
+    pub doc: Option<String>,
 }
@@ -0,0 +1,220 @@
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
+use crate::statement::{FuncStmt, Stmt, StmtKind};
+use crate::value::{LoxError, LoxValue};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const TEST_PREFIX: &str = "test_";
+// a doc comment line marking a function as a test whatever its name
+const TEST_ANNOTATION: &str = "@test";
+
+pub struct Outcome {
+    pub name: String,
+    pub time: Duration,
+    // the error that failed the test
+    pub failure: Option<String>,
+}
+
+// The `.lox` files under the paths, directories are searched recursively.
+pub fn discover(paths: &[String]) -> Result<Vec<PathBuf>, LoxError> {
+    let mut files = Vec::new();
+    for path in paths {
+        collect(Path::new(path), &mut files)?;
+    }
+    Ok(files)
+}
+
+fn collect(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), LoxError> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let unreadable = |error: std::io::Error| {
+        LoxError::Standard(format!(
+            "Error: unable to read '{}': {}",
+            path.display(),
+            error
+        ))
+    };
+    let mut entries = std::fs::read_dir(path)
+        .map_err(unreadable)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(unreadable)?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect(&entry, files)?;
+        } else if entry
+            .extension()
+            .is_some_and(|extension| extension == "lox")
+        {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+// Top level functions annotated with `/// @test`, or named `test_*` and taking
+// no arguments, a `test_*` function with parameters is a helper.
+fn tests(statements: &[Stmt]) -> Vec<&FuncStmt> {
+    statements
+        .iter()
+        .filter_map(|statement| match &statement.kind {
+            StmtKind::Function(func_stmt) => Some(func_stmt),
+            _ => None,
+        })
+        .filter(|func_stmt| {
+            func_stmt.name.identifier().starts_with(TEST_PREFIX) && func_stmt.params.is_empty()
+                || func_stmt
+                    .doc
+                    .as_deref()
+                    .is_some_and(|doc| doc.lines().any(|line| line.trim() == TEST_ANNOTATION))
+        })
+        .collect()
+}
+
+// Runs every test of the program, each in a fresh interpreter that first runs
+// the top level statements.
+pub fn run(source: &str) -> Result<Vec<Outcome>, Vec<LoxError>> {
+    let statements = Scanner::scan(source).and_then(|tokens| Parser::parse(&tokens))?;
+    Resolver::resolve(&statements, &Interpreter::new().global.borrow())?;
+
+    let mut outcomes = Vec::new();
+    for test in tests(&statements) {
+        let start = Instant::now();
+        let result = run_test(&statements, test);
+        outcomes.push(Outcome {
+            name: test.name.identifier().to_string(),
+            time: start.elapsed(),
+            failure: result.err().map(|error| error.to_string()),
+        });
+    }
+    Ok(outcomes)
+}
+
+fn run_test(statements: &[Stmt], test: &FuncStmt) -> Result<(), LoxError> {
+    if !test.params.is_empty() {
+        return Err(LoxError::Standard(format!(
+            "Error in line: {}, a test function takes no arguments.",
+            test.name.line
+        )));
+    }
+    let mut interpreter = Interpreter::new();
+    interpreter.interpret(statements)?;
+    // the top level code may have redefined the name
+    let value = interpreter.global.borrow().get(&test.name)?;
+    match value {
+        LoxValue::Callable(callable) if callable.arity() == 0 => {
+            callable.call(&mut interpreter, &[]).map(|_| ())
+        }
+        _ => Err(LoxError::Standard(format!(
+            "Error in line: {}, '{}' is no longer a test function.",
+            test.name.line,
+            test.name.identifier()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+var limit = 3;
+fun test_passes() {
+    assert(limit == 3, \"limit is three\");
+    assert_eq(limit + 1, 4);
+}
+fun test_fails() {
+    assert(limit > 5, \"limit is above five\");
+}
+/// Compares a number with a string.
+/// @test
+fun prints_the_same() {
+    assert_eq(limit, \"3\");
+}
+fun helper() {}
+fun test_helper(value) {}
+";
+
+    // Each test's name and failure, if any.
+    fn outcomes(source: &str) -> Vec<(String, Option<String>)> {
+        run(source)
+            .unwrap()
+            .into_iter()
+            .map(|outcome| (outcome.name, outcome.failure))
+            .collect()
+    }
+
+    #[test]
+    fn discovers_named_and_annotated_tests() {
+        let statements = Scanner::scan(SOURCE)
+            .and_then(|tokens| Parser::parse(&tokens))
+            .unwrap();
+        let names: Vec<_> = tests(&statements)
+            .iter()
+            .map(|test| test.name.identifier())
+            .collect();
+        assert_eq!(names, ["test_passes", "test_fails", "prints_the_same"]);
+    }
+
+    #[test]
+    fn reports_passes_and_failures() {
+        let outcomes = outcomes(SOURCE);
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(outcomes[0], ("test_passes".to_string(), None));
+        assert_eq!(
+            outcomes[1].1.as_deref(),
+            Some("Error in line: 7, assertion failed: limit is above five")
+        );
+        assert_eq!(
+            outcomes[2].1.as_deref(),
+            Some(
+                "Error in line: 12, values are not equal\n  left:  3\n  right: 3\n  \
+                 the values print the same but have different types"
+            )
+        );
+    }
+
+    #[test]
+    fn fails_a_test_redefined_by_the_top_level_code() {
+        assert_eq!(
+            outcomes("fun test_gone() {}\nvar test_gone = 1;\n"),
+            [(
+                "test_gone".to_string(),
+                Some("Error in line: 1, 'test_gone' is no longer a test function.".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn fails_an_annotated_test_taking_arguments() {
+        let outcomes = outcomes("/// @test\nfun check(value) {}\n");
+        assert_eq!(
+            outcomes[0].1.as_deref(),
+            Some("Error in line: 2, a test function takes no arguments.")
+        );
+    }
+
+    #[test]
+    fn discovers_lox_files_in_directories() {
+        let root = std::env::temp_dir().join(format!("rlox-discover-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("nested")).unwrap();
+        for file in ["b.lox", "a.lox", "notes.txt", "nested/c.lox"] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        let single = root.join("notes.txt").display().to_string();
+
+        let files = discover(&[root.display().to_string(), single]).unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+        let expected: Vec<_> = ["a.lox", "b.lox", "nested/c.lox", "notes.txt"]
+            .iter()
+            .map(|file| root.join(file))
+            .collect();
+        assert_eq!(files, expected);
+    }
+}
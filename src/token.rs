@@ -1,3 +1,12 @@
+use std::fmt;
+
+// The reserved words, which the scanner never gives as identifiers.
+pub const KEYWORDS: &[&str] = &[
+    "and", "class", "const", "else", "false", "for", "fun", "if", "nil", "or", "print", "return",
+    "super", "this", "true", "var", "while",
+];
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Debug, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
@@ -12,6 +21,8 @@ pub enum TokenType {
     Semicolon,
     Slash,
     Star,
+    Question,
+    Colon,
 
     // One or two character tokens.
     Bang,
@@ -27,10 +38,12 @@ pub enum TokenType {
     Identifier(String),
     String(String),
     Number(f64),
+    Integer(i64),
 
     // Keywords.
     And,
     Class,
+    Const,
     Else,
     False,
     Fun,
@@ -49,8 +62,88 @@ pub enum TokenType {
     EOF,
 }
 
+#[allow(clippy::struct_field_names)]
 #[derive(Clone, Debug)]
 pub struct Token {
     pub token_type: TokenType,
+    // the token as spelled in the source
+    pub lexeme: String,
+    pub line: usize,
+    pub column: usize,
+    // text of the `///` comments directly preceding this token, if any
+    pub doc: Option<String>,
+}
+
+impl Token {
+    // The name of an identifier token, or its spelling for any other token.
+    pub fn identifier(&self) -> &str {
+        match &self.token_type {
+            TokenType::Identifier(name) => name,
+            _ => &self.lexeme,
+        }
+    }
+}
+
+// A comment kept aside by the scanner so that tools can reproduce the source.
+#[derive(Clone, Debug)]
+pub struct Comment {
+    // the full comment including its delimiters
+    pub text: String,
     pub line: usize,
+    // whether code precedes the comment on the same line
+    pub trailing: bool,
+    // index of the token following the comment
+    pub next_token: usize,
+}
+
+// Formats a token as it is spelled in source code.
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lexeme = match self {
+            TokenType::LeftParen => "(",
+            TokenType::RightParen => ")",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
+            TokenType::Comma => ",",
+            TokenType::Dot => ".",
+            TokenType::Minus => "-",
+            TokenType::Plus => "+",
+            TokenType::Semicolon => ";",
+            TokenType::Slash => "/",
+            TokenType::Star => "*",
+            TokenType::Question => "?",
+            TokenType::Colon => ":",
+            TokenType::Bang => "!",
+            TokenType::BangEqual => "!=",
+            TokenType::Equal => "=",
+            TokenType::EqualEqual => "==",
+            TokenType::Greater => ">",
+            TokenType::GreaterEqual => ">=",
+            TokenType::Less => "<",
+            TokenType::LessEqual => "<=",
+            TokenType::Identifier(name) => name,
+            TokenType::String(string) => return write!(f, "\"{string}\""),
+            TokenType::Number(number) => return write!(f, "{number:?}"),
+            TokenType::Integer(integer) => return write!(f, "{integer}"),
+            TokenType::And => "and",
+            TokenType::Class => "class",
+            TokenType::Const => "const",
+            TokenType::Else => "else",
+            TokenType::False => "false",
+            TokenType::Fun => "fun",
+            TokenType::For => "for",
+            TokenType::If => "if",
+            TokenType::Nil => "nil",
+            TokenType::Or => "or",
+            TokenType::Print => "print",
+            TokenType::Return => "return",
+            TokenType::Super => "super",
+            TokenType::This => "this",
+            TokenType::True => "true",
+            TokenType::Var => "var",
+            TokenType::While => "while",
+            TokenType::EOF => "",
+        };
+        write!(f, "{lexeme}")
+    }
 }
@@ -1,31 +1,51 @@
 use crate::interpreter::Interpreter;
 use crate::statement::FuncStmt;
 use crate::token::TokenType;
-use crate::value::LoxValue::{Bool, Nil, Number};
+use crate::value::LoxValue::{Bool, Integer, Nil, Number};
 
 use crate::environment::Environment;
 use std::cell::RefCell;
 use std::fmt;
 use std::fmt::{Debug, Formatter};
+use std::fs::File;
+use std::io::BufReader;
 use std::rc::Rc;
 use thiserror::Error;
 
 #[derive(Debug, Clone)]
 pub enum LoxValue {
     Number(f64),
+    Integer(i64),
     String(String),
     Bool(bool),
     Nil,
     Callable(Callable),
+    File(Rc<RefCell<FileHandle>>),
+}
+
+// i64::MAX is not representable as an f64, so the upper bound is exclusive.
+pub const INTEGER_RANGE: std::ops::Range<f64> =
+    -9_223_372_036_854_775_808.0..9_223_372_036_854_775_808.0;
+
+// Compared exactly, converting the integer to a float would round it.
+#[allow(clippy::cast_possible_truncation, clippy::float_cmp)]
+fn integer_equals_float(integer: i64, number: f64) -> bool {
+    number.fract() == 0.0 && INTEGER_RANGE.contains(&number) && number as i64 == integer
 }
 
 impl PartialEq for LoxValue {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (Number(lhs), Number(rhs)) => lhs == rhs,
+            (Integer(lhs), Integer(rhs)) => lhs == rhs,
+            (Integer(integer), Number(number)) | (Number(number), Integer(integer)) => {
+                integer_equals_float(*integer, *number)
+            }
             (LoxValue::String(lhs), LoxValue::String(rhs)) => lhs == rhs,
             (Bool(lhs), Bool(rhs)) => lhs == rhs,
             (Nil, Nil) => true,
+            // a handle only equals itself
+            (LoxValue::File(lhs), LoxValue::File(rhs)) => Rc::ptr_eq(lhs, rhs),
             _ => false,
         }
     }
@@ -34,11 +54,14 @@ impl PartialEq for LoxValue {
 impl fmt::Display for LoxValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self {
-            Number(number) => write!(f, "{}", number),
-            LoxValue::String(string) => write!(f, "{}", string),
-            Bool(boolean) => write!(f, "{}", boolean),
+            // debug formatting keeps the trailing ".0" so floats never print like integers
+            Number(number) => write!(f, "{number:?}"),
+            Integer(integer) => write!(f, "{integer}"),
+            LoxValue::String(string) => write!(f, "{string}"),
+            Bool(boolean) => write!(f, "{boolean}"),
             Nil => write!(f, "nil"),
             LoxValue::Callable(callable) => std::fmt::Display::fmt(&callable, f),
+            LoxValue::File(file) => write!(f, "File : {}", file.borrow().path),
         }
     }
 }
@@ -51,16 +74,38 @@ impl LoxValue {
             _ => true,
         }
     }
+
+    #[allow(clippy::cast_precision_loss)]
+    pub fn as_float(&self) -> Option<f64> {
+        match &self {
+            Number(number) => Some(*number),
+            Integer(integer) => Some(*integer as f64),
+            _ => None,
+        }
+    }
+}
+
+// A file opened for reading line by line, the reader is dropped on close.
+#[derive(Debug)]
+pub struct FileHandle {
+    pub path: String,
+    pub reader: Option<BufReader<File>>,
 }
 
 #[derive(Error, Debug, Clone)]
 pub enum LoxError {
     #[error("{0}")]
     Standard(String),
+    // found in the source before running it, on `line`
+    #[error("{message}")]
+    Static { line: usize, message: String },
     // hack: we use return as an error so we can
     // unwind from the return keyword until we hit the calling function
     #[error("Return error")]
     Return(Return),
+    // likewise `exit` unwinds the whole program, carrying the exit code
+    #[error("the program exited with code {0}")]
+    Exit(i32),
 }
 
 #[derive(Debug, Clone)]
@@ -72,7 +117,7 @@ pub struct Return {
 pub enum Callable {
     Function {
         arity: usize,
-        func_stmt: FuncStmt,
+        func_stmt: Rc<FuncStmt>,
         environment: Rc<RefCell<Environment>>,
     },
     Native {
@@ -90,7 +135,7 @@ impl Debug for Callable {
 impl fmt::Display for Callable {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self {
-            Callable::Function { arity, .. } => write!(f, "Function : {}", arity),
+            Callable::Function { arity, .. } => write!(f, "Function : {arity}"),
             Callable::Native { .. } => write!(f, "Native function"),
         }
     }
@@ -113,20 +158,22 @@ impl Callable {
                 environment,
                 ..
             } => {
-                let new_environment = Environment::new_enclosed(Rc::clone(&environment));
+                let new_environment = Environment::new_enclosed(Rc::clone(environment));
 
                 for (param, arg) in func_stmt.params.iter().zip(arguments.iter()) {
                     if let TokenType::Identifier(lexeme) = &param.token_type {
-                        new_environment.borrow_mut().define(lexeme, arg);
+                        new_environment.borrow_mut().define(lexeme, arg)?;
                     }
                 }
+                interpreter.push_frame(func_stmt.name.identifier(), func_stmt.name.line)?;
                 let value = interpreter.execute_block(&func_stmt.body, new_environment);
+                interpreter.pop_frame();
                 if let Err(LoxError::Return(Return { value: Some(value) })) = value {
                     Ok(value)
                 } else if let Err(LoxError::Return(Return { value: None })) = value {
                     Ok(LoxValue::Nil)
                 } else {
-                    value.map(|_| LoxValue::Nil)
+                    value.map(|()| LoxValue::Nil)
                 }
             }
             Callable::Native { func, .. } => func(interpreter, arguments),
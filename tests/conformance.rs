@@ -0,0 +1,118 @@
+// Runs every .lox file under tests/lox and compares what rlox prints with the
+// expectations written in its comments:
+//
+//     print 1 + 2; // expect: 3
+//     print x;     // expect runtime error: Undefined variable: x
+//     print 1 +;   // expect error: Semicolon at line 1. Expected expression.
+//
+// Errors are printed after the output, prefixed with "Error: ". rlox must
+// exit with 65 when the program has an `expect error`, with 70 when it has an
+// `expect runtime error` and with 0 otherwise.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const EXPECT: &str = "// expect: ";
+const EXPECT_RUNTIME_ERROR: &str = "// expect runtime error: ";
+const EXPECT_ERROR: &str = "// expect error: ";
+
+#[test]
+fn conformance() {
+    let mut files = Vec::new();
+    collect(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/lox"),
+        &mut files,
+    );
+    assert!(!files.is_empty(), "no .lox files found under tests/lox");
+
+    let failures: Vec<String> = files.iter().filter_map(|file| check(file)).collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} files failed:\n\n{}",
+        failures.len(),
+        files.len(),
+        failures.join("\n")
+    );
+}
+
+fn collect(directory: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(directory)
+        .unwrap_or_else(|error| panic!("unable to read {}: {}", directory.display(), error))
+        .map(|entry| entry.expect("unable to read directory entry").path())
+        .collect();
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect(&entry, files);
+        } else if entry
+            .extension()
+            .is_some_and(|extension| extension == "lox")
+        {
+            files.push(entry);
+        }
+    }
+}
+
+// The lines rlox should print for the program.
+fn expected_output(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let comment = &line[line.find("// expect")?..];
+            if let Some(text) = comment.strip_prefix(EXPECT) {
+                return Some(text.to_string());
+            }
+            comment
+                .strip_prefix(EXPECT_RUNTIME_ERROR)
+                .or_else(|| comment.strip_prefix(EXPECT_ERROR))
+                .map(|message| format!("Error: {}", message))
+        })
+        .collect()
+}
+
+// The exit code rlox should end with for the program.
+fn expected_status(source: &str) -> i32 {
+    if source.contains(EXPECT_ERROR) {
+        65
+    } else if source.contains(EXPECT_RUNTIME_ERROR) {
+        70
+    } else {
+        0
+    }
+}
+
+// A description of the mismatch, if the output isn't the expected one.
+fn check(file: &Path) -> Option<String> {
+    let source = std::fs::read_to_string(file)
+        .unwrap_or_else(|error| panic!("unable to read {}: {}", file.display(), error));
+    let expected = expected_output(&source);
+    let expected_status = expected_status(&source);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .arg(file)
+        .output()
+        .unwrap_or_else(|error| panic!("unable to run rlox: {}", error));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let actual: Vec<String> = stdout.lines().map(str::to_string).collect();
+
+    let status = output.status.code();
+
+    if actual == expected && status == Some(expected_status) {
+        return None;
+    }
+    Some(format!(
+        "{}\n  expected (exit code {}):\n{}\n  actual (exit code {}):\n{}\n",
+        file.display(),
+        expected_status,
+        indent(&expected),
+        status.map_or_else(|| "none".to_string(), |code| code.to_string()),
+        indent(&actual)
+    ))
+}
+
+fn indent(lines: &[String]) -> String {
+    lines
+        .iter()
+        .map(|line| format!("    {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,156 @@
+// Drives `rlox dap` the way an editor does, over stdin and stdout with
+// Content-Length framing, and checks where the program stops.
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdout, Command, Stdio};
+
+const PROGRAM: &str = "\
+var total = 0;
+for (var i = 0; i < 3; i = i + 1) {
+  total = total + i;
+}
+print total;
+";
+
+struct Client {
+    child: Child,
+    stdout: BufReader<ChildStdout>,
+    seq: u64,
+}
+
+impl Client {
+    fn spawn() -> Client {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+            .arg("dap")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap_or_else(|error| panic!("unable to run rlox: {}", error));
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        Client {
+            child,
+            stdout,
+            seq: 0,
+        }
+    }
+
+    fn send(&mut self, command: &str, arguments: Value) {
+        self.seq += 1;
+        let message = json!({
+            "seq": self.seq,
+            "type": "request",
+            "command": command,
+            "arguments": arguments,
+        })
+        .to_string();
+        let stdin = self.child.stdin.as_mut().expect("stdin is piped");
+        write!(
+            stdin,
+            "Content-Length: {}\r\n\r\n{}",
+            message.len(),
+            message
+        )
+        .and_then(|()| stdin.flush())
+        .unwrap_or_else(|error| panic!("unable to send {}: {}", command, error));
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = None;
+        loop {
+            let mut header = String::new();
+            let read = self
+                .stdout
+                .read_line(&mut header)
+                .unwrap_or_else(|error| panic!("unable to read a header: {}", error));
+            assert!(read > 0, "the adapter closed its output");
+            let header = header.trim();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(value) = header.strip_prefix("Content-Length:") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+        let mut body = vec![0; length.expect("a Content-Length header")];
+        self.stdout
+            .read_exact(&mut body)
+            .unwrap_or_else(|error| panic!("unable to read a message: {}", error));
+        serde_json::from_slice(&body).expect("a JSON message")
+    }
+
+    // Reads messages until the one matching `kind` ("response" or "event")
+    // and `name`, skipping output and other events.
+    fn until(&mut self, kind: &str, name: &str) -> Value {
+        let key = if kind == "event" { "event" } else { "command" };
+        loop {
+            let message = self.receive();
+            if message["type"] == kind && message[key] == name {
+                return message;
+            }
+        }
+    }
+
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.send(command, arguments);
+        let response = self.until("response", command);
+        assert_eq!(
+            response["success"], true,
+            "{} failed: {}",
+            command, response
+        );
+        response
+    }
+
+    // The line the program is paused on.
+    fn paused_line(&mut self) -> u64 {
+        let trace = self.request("stackTrace", json!({ "threadId": 1 }));
+        trace["body"]["stackFrames"][0]["line"]
+            .as_u64()
+            .expect("a stack frame")
+    }
+}
+
+fn program() -> PathBuf {
+    let path = std::env::temp_dir().join(format!("rlox-dap-{}.lox", std::process::id()));
+    std::fs::write(&path, PROGRAM)
+        .unwrap_or_else(|error| panic!("unable to write {}: {}", path.display(), error));
+    path
+}
+
+#[test]
+fn breakpoint_in_a_loop_stops_on_every_iteration() {
+    let path = program();
+    let mut client = Client::spawn();
+    client.request("initialize", json!({ "adapterID": "rlox" }));
+    client.until("event", "initialized");
+    client.request("launch", json!({ "program": path }));
+    let breakpoints = client.request(
+        "setBreakpoints",
+        json!({ "source": { "path": path }, "breakpoints": [{ "line": 3 }] }),
+    );
+    assert_eq!(breakpoints["body"]["breakpoints"][0]["verified"], true);
+    client.request("configurationDone", json!({}));
+
+    let mut stops = Vec::new();
+    loop {
+        let message = client.receive();
+        match (message["type"].as_str(), message["event"].as_str()) {
+            (Some("event"), Some("stopped")) => {
+                let reason = message["body"]["reason"].as_str().map(str::to_string);
+                stops.push((reason, client.paused_line()));
+                client.request("continue", json!({ "threadId": 1 }));
+            }
+            (Some("event"), Some("terminated")) => break,
+            _ => {}
+        }
+    }
+    let breakpoint = (Some("breakpoint".to_string()), 3);
+    assert_eq!(stops, vec![breakpoint; 3]);
+
+    client.request("disconnect", json!({}));
+    drop(client.child.stdin.take());
+    let status = client.child.wait().expect("the adapter exits");
+    assert!(status.success(), "the adapter exited with {}", status);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,70 @@
+// Runs `rlox test` on directories of test files and checks the summary it
+// prints and the code it exits with.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// A fresh directory holding the files, named after the test using it.
+fn directory(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("rlox-test-{}-{}", name, std::process::id()));
+    for (file, source) in files {
+        let path = directory.join(file);
+        std::fs::create_dir_all(path.parent().expect("a parent directory"))
+            .and_then(|()| std::fs::write(&path, source))
+            .unwrap_or_else(|error| panic!("unable to write {}: {}", path.display(), error));
+    }
+    directory
+}
+
+// The last line printed and the exit code.
+fn run(directory: &Path) -> (String, i32) {
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .arg("test")
+        .arg(directory)
+        .output()
+        .unwrap_or_else(|error| panic!("unable to run rlox: {}", error));
+    let _ = std::fs::remove_dir_all(directory);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let summary = stdout.lines().last().unwrap_or_default();
+    // drop the time, it changes from run to run
+    let summary = summary.split(" in ").next().unwrap_or_default().to_string();
+    (summary, output.status.code().expect("an exit code"))
+}
+
+#[test]
+fn passing_tests_exit_with_zero() {
+    let directory = directory(
+        "passing",
+        &[
+            ("math.lox", "fun test_add() { assert_eq(1 + 1, 2); }\n"),
+            (
+                "nested/text.lox",
+                "/// @test\nfun joins() { assert(\"a\" + \"b\" == \"ab\", \"joined\"); }\n",
+            ),
+        ],
+    );
+    assert_eq!(run(&directory), ("2 passed, 0 failed".to_string(), 0));
+}
+
+#[test]
+fn a_failing_test_exits_with_one() {
+    let directory = directory(
+        "failing",
+        &[(
+            "math.lox",
+            "fun test_add() { assert_eq(1 + 1, 2); }\nfun test_wrong() { assert_eq(1 + 1, 3); }\n",
+        )],
+    );
+    assert_eq!(run(&directory), ("1 passed, 1 failed".to_string(), 1));
+}
+
+#[test]
+fn a_file_that_does_not_parse_exits_with_65() {
+    let directory = directory(
+        "broken",
+        &[
+            ("broken.lox", "fun test_broken( {}\n"),
+            ("math.lox", "fun test_add() { assert_eq(1 + 1, 2); }\n"),
+        ],
+    );
+    assert_eq!(run(&directory), ("1 passed, 0 failed".to_string(), 65));
+}